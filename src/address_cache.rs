@@ -0,0 +1,75 @@
+use asr::Address;
+// the offsets are cached in the settings of the layout, the tests have none so they always scan
+#[cfg(target_arch = "wasm32")]
+use asr::settings::Map;
+
+use crate::game_memory::GameMemory;
+
+// settings map keys, the offsets are relative to the main module and saved as hex strings
+#[cfg(target_arch = "wasm32")]
+const MODULE_ID_KEY: &str = "cache_module_id";
+pub const ROOM_ID_KEY: &str = "cache_room_id_offset";
+pub const ROOM_NAMES_POINTER_KEY: &str = "cache_room_names_pointer_offset";
+#[cfg(target_arch = "wasm32")]
+const OFFSET_KEYS: [&str; 2] = [ROOM_ID_KEY, ROOM_NAMES_POINTER_KEY];
+
+/**
+ * Identifies a build of the game by the module size and the PE header timestamp, the cached offsets are only valid for the same build
+ */
+pub fn module_identity<M: GameMemory>(
+    memory: &M,
+    main_address: Address,
+    module_size: u64,
+) -> Option<String> {
+    // e_lfanew in the DOS header points to the PE header, its TimeDateStamp is 8 bytes in
+    let pe_header_offset = memory
+        .read_u32(Address::new(main_address.value() + 0x3C))
+        .ok()?;
+    let timestamp = memory
+        .read_u32(Address::new(
+            main_address.value() + pe_header_offset as u64 + 0x8,
+        ))
+        .ok()?;
+    Some(format!("{module_size:X}-{timestamp:X}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn stored_module_id(settings_map: &Map) -> Option<String> {
+    settings_map.get(MODULE_ID_KEY)?.get_string()
+}
+
+/**
+ * the offset saved for this build of the game, None if there's none or it was found in a different build
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn load_offset(module_id: &str, key: &str) -> Option<u64> {
+    let settings_map = Map::load();
+    if stored_module_id(&settings_map)? != module_id {
+        return None;
+    }
+    u64::from_str_radix(&settings_map.get(key)?.get_string()?, 16).ok()
+}
+
+/**
+ * saves an offset found by scanning, the offsets of another build are forgotten
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn store_offset(module_id: &str, key: &str, offset: u64) {
+    let settings_map = Map::load();
+    if stored_module_id(&settings_map).as_deref() != Some(module_id) {
+        for offset_key in OFFSET_KEYS {
+            settings_map.insert(offset_key, "");
+        }
+        settings_map.insert(MODULE_ID_KEY, module_id);
+    }
+    settings_map.insert(key, format!("{offset:X}").as_str());
+    settings_map.store();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_offset(_module_id: &str, _key: &str) -> Option<u64> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store_offset(_module_id: &str, _key: &str, _offset: u64) {}
@@ -0,0 +1,33 @@
+//! Replays a trace recorded with the "Record a trace of the memory values" setting and prints the timer actions.
+//!
+//! `$ cargo run --bin replay_trace -- <trace or log file>`
+
+use std::{env, fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("Usage: replay_trace <trace or log file>");
+        return ExitCode::FAILURE;
+    };
+
+    let trace = match fs::read_to_string(&path) {
+        Ok(trace) => trace,
+        Err(err) => {
+            eprintln!("Could not read {path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match pizza_tower_ls_wasm::replay_report(&trace) {
+        Ok(report) => {
+            for line in report {
+                println!("{line}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
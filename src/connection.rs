@@ -0,0 +1,81 @@
+use asr::future::next_tick;
+
+use crate::logging;
+
+/**
+ * Where the splitter is in connecting to the game, every stage yields to the runtime while it waits
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConnectionStage {
+    /// the process was found
+    Attached,
+    /// looking for the memory addresses
+    Scanning,
+    /// the game is still loading and there's no room yet
+    WaitingForRoom,
+    /// everything is read from the "-livesplit" buffer
+    ReadyBuffer,
+    /// no buffer, the room names are read from the game's room array
+    ReadyFallback,
+    /// the game closed or the memory can't be read anymore, everything starts again
+    Lost,
+}
+
+impl ConnectionStage {
+    /**
+     * prints the new stage to the log, the splitter status shows it in the variables
+     */
+    pub fn announce(self) {
+        let message = match self {
+            ConnectionStage::Attached => "Connected to Pizza Tower the pizzapasta game!!!",
+            ConnectionStage::Scanning => "Looking for the memory addresses...",
+            ConnectionStage::WaitingForRoom => "Waiting for the game to start...",
+            ConnectionStage::ReadyBuffer => "Ready, using the \"-livesplit\" buffer.",
+            ConnectionStage::ReadyFallback => {
+                "Ready, using the room names array. Is the \"-livesplit\" launch option set?"
+            }
+            ConnectionStage::Lost => "Lost the connection to the game, retrying...",
+        };
+        logging::info(message);
+    }
+}
+
+// the first retry waits this many ticks, doubling every time up to the max
+const BACKOFF_FIRST_DELAY_TICKS: u32 = 4;
+const BACKOFF_MAX_DELAY_TICKS: u32 = 160;
+
+/**
+ * Waits longer after every failed attempt, for a limited amount of attempts
+ */
+pub struct Backoff {
+    attempt: u32,
+    max_attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            attempt: 0,
+            max_attempts,
+        }
+    }
+
+    /**
+     * Waits before the next attempt, returns false without waiting when there are no attempts left
+     */
+    pub async fn wait(&mut self) -> bool {
+        if self.attempt >= self.max_attempts {
+            return false;
+        }
+
+        let delay_ticks = BACKOFF_FIRST_DELAY_TICKS
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(BACKOFF_MAX_DELAY_TICKS);
+        for _ in 0..delay_ticks {
+            next_tick().await;
+        }
+
+        self.attempt += 1;
+        true
+    }
+}
@@ -0,0 +1,293 @@
+#[cfg(target_arch = "wasm32")]
+use asr::Process;
+use asr::{signature::Signature, Address};
+
+/**
+ * Why a C string couldn't be read
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CStringError {
+    /// a chunk of the string couldn't be read
+    ReadFailed { address: Address },
+    /// no 0 byte before the max length
+    Truncated { max_length: usize },
+    /// the bytes before the 0 aren't UTF-8
+    InvalidUtf8,
+}
+
+/**
+ * A signature of any length, so the signatures that can find the same target don't need the same length
+ */
+pub trait SignaturePattern {
+    fn length(&self) -> usize;
+
+    /**
+     * where the signature starts in the bytes
+     */
+    fn scan_bytes(&self, bytes: &[u8]) -> Option<usize>;
+
+    #[cfg(target_arch = "wasm32")]
+    fn scan_process(&self, process: &Process, range: (Address, u64)) -> Option<Address>;
+}
+
+impl<const N: usize> SignaturePattern for Signature<N> {
+    fn length(&self) -> usize {
+        N
+    }
+
+    fn scan_bytes(&self, bytes: &[u8]) -> Option<usize> {
+        self.scan(bytes)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn scan_process(&self, process: &Process, range: (Address, u64)) -> Option<Address> {
+        self.scan_process_range(process, range)
+    }
+}
+
+/// chunks of a C string read are aligned to this so none of them crosses a page boundary
+const C_STRING_CHUNK_SIZE: u64 = 0x40;
+
+/**
+ * Anything the memory reading code can read from, the live game or a synthetic memory image
+ */
+pub trait GameMemory {
+    /**
+     * fills the whole buffer with the bytes found at the address, fails if any of them can't be read
+     */
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Result<(), ()>;
+
+    /**
+     * every memory range of the game as (start address, size)
+     */
+    fn memory_ranges(&self) -> Vec<(Address, u64)>;
+
+    /**
+     * looks for the signature inside the range, returning the address where it starts
+     */
+    fn scan(
+        &self,
+        signature: &dyn SignaturePattern,
+        (range_start, range_size): (Address, u64),
+    ) -> Option<Address> {
+        const CHUNK_SIZE: u64 = 0x1000;
+
+        let range_end = range_start.value() + range_size;
+        let mut chunk_start = range_start.value();
+        let mut buf = Vec::new();
+
+        while chunk_start < range_end {
+            // overlap the chunks so a signature between two of them isn't missed
+            let chunk_size =
+                (CHUNK_SIZE + signature.length() as u64 - 1).min(range_end - chunk_start);
+            buf.resize(chunk_size as usize, 0);
+
+            if self.read_bytes(Address::new(chunk_start), &mut buf).is_ok() {
+                if let Some(index) = signature.scan_bytes(&buf) {
+                    return Some(Address::new(chunk_start + index as u64));
+                }
+            }
+
+            chunk_start += CHUNK_SIZE;
+        }
+
+        None
+    }
+
+    fn read_array<const N: usize>(&self, address: Address) -> Result<[u8; N], ()> {
+        let mut buf = [0; N];
+        self.read_bytes(address, &mut buf)?;
+        Ok(buf)
+    }
+
+    /**
+     * reads a 0 terminated UTF-8 string into the buffer, its length is the max length of the string.
+     * The string is read in small aligned chunks until the 0, so short strings are cheap and the end of readable memory right after one isn't a problem
+     */
+    fn read_c_string<'a>(
+        &self,
+        address: Address,
+        buf: &'a mut [u8],
+    ) -> Result<&'a str, CStringError> {
+        let mut length = 0;
+        loop {
+            if length == buf.len() {
+                return Err(CStringError::Truncated {
+                    max_length: buf.len(),
+                });
+            }
+            let chunk_address = address.value() + length as u64;
+            let chunk_size = (C_STRING_CHUNK_SIZE - chunk_address % C_STRING_CHUNK_SIZE) as usize;
+            let chunk_end = (length + chunk_size).min(buf.len());
+            let chunk = &mut buf[length..chunk_end];
+            self.read_bytes(Address::new(chunk_address), chunk)
+                .map_err(|_| CStringError::ReadFailed {
+                    address: Address::new(chunk_address),
+                })?;
+            if let Some(end) = chunk.iter().position(|byte| *byte == 0) {
+                length += end;
+                break;
+            }
+            length = chunk_end;
+        }
+        core::str::from_utf8(&buf[..length]).map_err(|_| CStringError::InvalidUtf8)
+    }
+
+    fn read_u8(&self, address: Address) -> Result<u8, ()> {
+        self.read_array::<1>(address).map(|bytes| bytes[0])
+    }
+
+    fn read_i32(&self, address: Address) -> Result<i32, ()> {
+        self.read_array(address).map(i32::from_le_bytes)
+    }
+
+    fn read_u32(&self, address: Address) -> Result<u32, ()> {
+        self.read_array(address).map(u32::from_le_bytes)
+    }
+
+    fn read_u64(&self, address: Address) -> Result<u64, ()> {
+        self.read_array(address).map(u64::from_le_bytes)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl GameMemory for Process {
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Result<(), ()> {
+        self.read_into_buf(address, buf).map_err(|_| ())
+    }
+
+    fn memory_ranges(&self) -> Vec<(Address, u64)> {
+        Process::memory_ranges(self)
+            .filter_map(|range| Some((range.address().ok()?, range.size().ok()?)))
+            .collect()
+    }
+
+    fn scan(&self, signature: &dyn SignaturePattern, range: (Address, u64)) -> Option<Address> {
+        signature.scan_process(self, range)
+    }
+}
+
+/**
+ * A fake game made of memory regions filled by hand, to run the memory code outside of the runtime
+ */
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryImage {
+    regions: Vec<(Address, Vec<u8>)>,
+}
+
+#[cfg(test)]
+impl MemoryImage {
+    /**
+     * adds a region, regions should not overlap
+     */
+    pub fn add_region(&mut self, start: Address, bytes: Vec<u8>) -> &mut Self {
+        self.regions.push((start, bytes));
+        self
+    }
+
+    /**
+     * overwrites bytes inside an existing region
+     */
+    pub fn write(&mut self, address: Address, bytes: &[u8]) -> Result<(), ()> {
+        for (start, region) in self.regions.iter_mut() {
+            let Some(offset) = address.value().checked_sub(start.value()) else {
+                continue;
+            };
+            let offset = offset as usize;
+            if offset + bytes.len() <= region.len() {
+                region[offset..offset + bytes.len()].copy_from_slice(bytes);
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+}
+
+#[cfg(test)]
+impl GameMemory for MemoryImage {
+    fn read_bytes(&self, address: Address, buf: &mut [u8]) -> Result<(), ()> {
+        for (start, region) in self.regions.iter() {
+            let Some(offset) = address.value().checked_sub(start.value()) else {
+                continue;
+            };
+            let offset = offset as usize;
+            if offset + buf.len() <= region.len() {
+                buf.copy_from_slice(&region[offset..offset + buf.len()]);
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
+    fn memory_ranges(&self) -> Vec<(Address, u64)> {
+        self.regions
+            .iter()
+            .map(|(start, region)| (*start, region.len() as u64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // readable memory ends on a page, which is always on a chunk boundary
+    const REGION: u64 = 0x1000;
+    const REGION_SIZE: usize = 0x40;
+
+    /**
+     * one region of memory with the bytes at its end, zeros before them
+     */
+    fn memory_ending_with(bytes: &[u8]) -> (MemoryImage, Address) {
+        let mut region = vec![0; REGION_SIZE.max(bytes.len())];
+        let start = region.len() - bytes.len();
+        region[start..].copy_from_slice(bytes);
+        let mut memory = MemoryImage::default();
+        memory.add_region(Address::new(REGION), region);
+        (memory, Address::new(REGION + start as u64))
+    }
+
+    #[test]
+    fn a_string_can_end_at_the_end_of_readable_memory() {
+        let (memory, address) = memory_ending_with(b"entrance_1\0");
+        let mut buf = [0; 0x200];
+
+        assert_eq!(memory.read_c_string(address, &mut buf), Ok("entrance_1"));
+    }
+
+    #[test]
+    fn a_string_without_its_end_in_readable_memory_fails_to_read() {
+        let (memory, address) = memory_ending_with(b"entrance_1");
+        let mut buf = [0; 0x200];
+
+        assert_eq!(
+            memory.read_c_string(address, &mut buf),
+            Err(CStringError::ReadFailed {
+                address: Address::new(REGION + REGION_SIZE as u64)
+            })
+        );
+    }
+
+    #[test]
+    fn a_string_longer_than_the_buffer_is_truncated() {
+        let (memory, address) = memory_ending_with(&[b'a'; 0x100]);
+        let mut buf = [0; 0x10];
+
+        assert_eq!(
+            memory.read_c_string(address, &mut buf),
+            Err(CStringError::Truncated { max_length: 0x10 })
+        );
+    }
+
+    #[test]
+    fn a_string_that_isnt_utf8_is_an_error() {
+        let (memory, address) = memory_ending_with(b"tower_\xFF\xFE\0");
+        let mut buf = [0; 0x200];
+
+        assert_eq!(
+            memory.read_c_string(address, &mut buf),
+            Err(CStringError::InvalidUtf8)
+        );
+    }
+}
@@ -1,269 +1,464 @@
-use asr::{
-    future::{next_tick, IntoOption},
-    print_message,
-    settings::Gui,
-    time::Duration,
-    timer::{self, TimerState},
-    watcher::Pair,
-    Process,
+// the split logic is also built for the host to replay traces, everything that talks to the runtime is only built for wasm.
+// the memory code is also built for its tests, which read a MemoryImage instead of the game
+use memory_fields::MemoryValues;
+#[cfg(target_arch = "wasm32")]
+use {
+    asr::{future::next_tick, print_message, settings::Gui, time::Duration, timer, Process},
+    connection::{Backoff, ConnectionStage},
+    memory::{refresh_mem_values, Rescan, RescanStep},
+    memory_error::{MemoryError, MemoryTarget, Recovery},
+    settings::{TRANSITION_RULES_KEY, TRANSITION_RULES_MAX_LENGTH},
+    split_engine::{SplitEngine, TimerAction},
+    status::SplitterStatus,
+    trace::TraceRecorder,
+    watchdog::{MemoryWatchdog, StaleMemory},
 };
-use memory::refresh_mem_values;
-use rooms_ids::Level;
-use settings::TimerMode;
+#[cfg(target_arch = "wasm32")]
 asr::async_main!(stable);
 
+#[cfg(any(target_arch = "wasm32", test))]
+mod address_cache;
+#[cfg(target_arch = "wasm32")]
+mod connection;
+#[cfg(any(target_arch = "wasm32", test))]
+mod game_memory;
+#[cfg(any(target_arch = "wasm32", test))]
+mod logging;
+#[cfg(any(target_arch = "wasm32", test))]
 mod memory;
+#[cfg(any(target_arch = "wasm32", test))]
+mod memory_error;
+mod memory_fields;
+mod room_rules;
 mod rooms_ids;
 mod settings;
-
+#[cfg(any(target_arch = "wasm32", test))]
+mod speedrun_buffer;
+mod split_engine;
+#[cfg(target_arch = "wasm32")]
+mod status;
+mod trace;
+#[cfg(any(target_arch = "wasm32", test))]
+mod variables;
+#[cfg(target_arch = "wasm32")]
+mod watchdog;
+
+pub use trace::replay_report;
+
+#[cfg(any(target_arch = "wasm32", test))]
 #[derive(Default, Clone)]
 struct MemoryAddresses {
     main_address: Option<asr::Address>,
+    main_module_size: Option<u64>,
     room_id: Option<asr::Address>,
     room_names: Option<asr::Address>,
     buffer_helper: Option<asr::Address>,
 }
 
-#[derive(Default)]
-struct MemoryValues {
-    game_version: Pair<String>,
-    room_id: Pair<i32>,
-    room_name: Pair<String>,
-    file_seconds: Pair<f64>,
-    file_minutes: Pair<f64>,
-    level_seconds: Pair<f64>,
-    level_minutes: Pair<f64>,
-    end_of_level: Pair<bool>,
-    boss_hp: Pair<u8>,
-}
-
+#[cfg(target_arch = "wasm32")]
 const MAIN_MODULE: &str = "PizzaTower.exe";
+#[cfg(target_arch = "wasm32")]
 const TICK_RATE_MAIN_LOOP: f64 = 240.0;
+#[cfg(target_arch = "wasm32")]
 const TICK_RATE_INIT: f64 = 40.0;
+// retries of each signature scan before starting over, the waits between them grow each time
+#[cfg(target_arch = "wasm32")]
+const SCAN_RETRIES: u32 = 5;
+// two minutes at the init tick rate
+#[cfg(target_arch = "wasm32")]
+const WAIT_FOR_ROOM_TIMEOUT_TICKS: u32 = 40 * 120;
+// one second of failed reads at the main loop tick rate before starting over
+#[cfg(target_arch = "wasm32")]
+const READ_RETRY_TICKS: u32 = 240;
+
+/**
+ * what has to happen every time the settings are read from the GUI
+ */
+#[cfg(target_arch = "wasm32")]
+fn apply_settings(settings: &mut settings::Settings, status: &mut SplitterStatus) {
+    if settings.timer_mode.changed() {
+        settings.load_default_settings_for_mode();
+    }
+    status.set_timer_mode(settings.timer_mode.current);
 
+    variables::set_show_debug(settings.debug_show_variables);
+    logging::set_level(settings.log_level.current);
+    if settings.debug_dump_log.changed() {
+        logging::dump();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
 async fn main() {
     // startup
     asr::set_tick_rate(TICK_RATE_INIT);
     let mut settings = settings::Settings::register();
+    let mut status = SplitterStatus::new();
 
     loop {
         // check if settings GUI changes
         settings.update();
-        if settings.timer_mode.changed() {
-            settings.load_default_settings_for_mode();
-        }
+        apply_settings(&mut settings, &mut status);
 
         let process_option = Process::attach(MAIN_MODULE);
 
         let mut mem_addresses = MemoryAddresses::default();
         let mut mem_values = MemoryValues::default();
 
-        let process;
-        match process_option {
-            Some(process_found) => {
-                process = process_found;
-                mem_addresses.main_address = process.get_module_address(MAIN_MODULE).into_option();
-            }
-            None => {
-                next_tick().await;
-                continue;
-            }
-        }
-
-        print_message("Connected to Pizza Tower the pizzapasta game!!!");
-
-        process.until_closes(async {
-
-            // init
-            if let Ok(address) = memory::room_id_sigscan_start(&process, mem_addresses.clone()) {
-                mem_addresses.room_id = Some(address);
-            } else {
-                mem_addresses.room_id = None;
-            }
-
-            if mem_addresses.room_id.is_some() {
-                if let Ok(room_id_result) = process.read(mem_addresses.main_address.unwrap_or(asr::Address::default()).value() + mem_addresses.room_id.unwrap().value()) {
-                    mem_values.room_id.current = room_id_result
-                } else {
-                    mem_values.room_id.current = 0;
-                    print_message(&format!("Could not read room ID before stall that waits for the game opening. Using {}.", mem_values.room_id.current));
-                }
-                if mem_values.room_id.current == 0 {
-                    print_message("Waiting for the game to start...");
-                }
-                while mem_values.room_id.current == 0 {
-                    if mem_values.room_id.current == 0 {
-                        if let Ok(value) = process.read::<i32>(mem_addresses.main_address.unwrap_or(asr::Address::default()).value() + mem_addresses.room_id.unwrap().value()) {
-                            mem_values.room_id.current = value;
-                        } else {
+        let Some(process) = process_option else {
+            next_tick().await;
+            continue;
+        };
+
+        status.set_stage(ConnectionStage::Attached);
+
+        process
+            .until_closes(async {
+                // the module can be missing for a moment while the game starts
+                let mut backoff = Backoff::new(SCAN_RETRIES);
+                let (main_address, main_module_size) = loop {
+                    match process.get_module_range(MAIN_MODULE) {
+                        Ok(range) => break range,
+                        Err(_) => status.report_error(&MemoryError::NotFound {
+                            what: MemoryTarget::MainModule,
+                        }),
+                    }
+                    if !backoff.wait().await {
+                        logging::error(&format!(
+                            "Could not find the main module after {} tries.",
+                            SCAN_RETRIES + 1
+                        ));
+                        return;
+                    }
+                };
+                mem_addresses.main_address = Some(main_address);
+                mem_addresses.main_module_size = Some(main_module_size);
+
+                // init
+                status.set_stage(ConnectionStage::Scanning);
+                let mut backoff = Backoff::new(SCAN_RETRIES);
+                mem_addresses.room_id = loop {
+                    match memory::room_id_sigscan_start(&process, mem_addresses.clone()).await {
+                        Ok(address) => break Some(address),
+                        Err(err) => status.report_error(&err),
+                    }
+                    if !backoff.wait().await {
+                        break None;
+                    }
+                };
+                let Some(room_id_offset) = mem_addresses.room_id else {
+                    logging::error(&format!(
+                        "Could not find the room ID after {} tries.",
+                        SCAN_RETRIES + 1
+                    ));
+                    return;
+                };
+
+                // the room id is 0 until the game opens, yield every tick until then
+                let room_id_address =
+                    asr::Address::new(main_address.value() + room_id_offset.value());
+                let mut waited_ticks = 0;
+                loop {
+                    match process.read::<i32>(room_id_address) {
+                        Ok(0) => {}
+                        Ok(room_id) => {
+                            mem_values.room_id.current = room_id;
                             break;
                         }
+                        Err(_) => {
+                            status.report_error(&MemoryError::ReadFailed {
+                                what: MemoryTarget::RoomId,
+                                address: room_id_address,
+                            });
+                            return;
+                        }
+                    }
+                    if waited_ticks == 0 {
+                        status.set_stage(ConnectionStage::WaitingForRoom);
                     }
+                    if waited_ticks >= WAIT_FOR_ROOM_TIMEOUT_TICKS {
+                        logging::warn(
+                            "The game didn't reach the title screen in time, scanning again...",
+                        );
+                        return;
+                    }
+                    waited_ticks += 1;
+                    next_tick().await;
                 }
-            }
-
-            mem_addresses.buffer_helper = memory::buffer_helper_sigscan_init(&process).into_option();
-            // not needed if helper was found
-            if mem_addresses.buffer_helper.is_none() {
-                mem_addresses.room_names = memory::room_name_array_sigscan_start(&process).into_option();
-            }
-
-            // ready for main loop
-            if mem_addresses.room_names.is_some() || mem_addresses.buffer_helper.is_some() {
 
-                // variables declaration for the main loop
-                let mut current_level = rooms_ids::Level::Unknown;
-                let mut igt_file_secs_calculated: Pair<f64> = Pair::default();
-                let mut igt_level_secs_calculated: Pair<f64> = Pair::default();
-
-                let mut ng_plus_offset_seconds: Option<f64> = None;
-                let mut iw_offset_seconds: Option<f64> = None;
-
-                let mut enable_full_game_split = false;
-                let mut ctop_oob_split = false; // should only happen once per run
+                mem_addresses.buffer_helper =
+                    memory::buffer_helper_sigscan_init(&process).await.ok();
+                // not needed if helper was found
+                if mem_addresses.buffer_helper.is_some() {
+                    status.set_stage(ConnectionStage::ReadyBuffer);
+                } else {
+                    let mut backoff = Backoff::new(SCAN_RETRIES);
+                    mem_addresses.room_names = loop {
+                        match memory::room_name_array_sigscan_start(
+                            &process,
+                            &mem_addresses,
+                            mem_values.room_id.current,
+                        )
+                        .await
+                        {
+                            Ok(address) => break Some(address),
+                            Err(err) => status.report_error(&err),
+                        }
+                        if !backoff.wait().await {
+                            break None;
+                        }
+                    };
+                    if mem_addresses.room_names.is_none() {
+                        logging::error(&format!(
+                            "Could not find the room names array after {} tries.",
+                            SCAN_RETRIES + 1
+                        ));
+                        return;
+                    }
+                    status.set_stage(ConnectionStage::ReadyFallback);
+                }
 
-                let mut last_room_split_name = String::new();
-                let mut last_room_split_time = 0.0;
+                // ready for main loop
+                let mut split_engine = SplitEngine::default();
+                let mut trace_recorder = TraceRecorder::default();
+                // replaying a trace starts from a new split engine, so recording only starts with one.
+                // turning recording on later waits for the next attach
+                let mut record_trace = settings.debug_record_trace;
+                let mut buffer_rescan = Rescan::new(MemoryTarget::Buffer);
+                let mut room_names_rescan = Rescan::new(MemoryTarget::RoomNamesArray);
+                let mut memory_watchdog = MemoryWatchdog::default();
+                // what the watchdog said is stale, the old addresses are still read until a new one is found
+                let mut stale_buffer = false;
+                let mut stale_room_names = false;
+                // a pass over the heap didn't find the stale buffer again
+                let mut buffer_gone = false;
+                let mut failed_reads = 0;
+                let mut transition_rules_too_long = false;
 
                 asr::set_tick_rate(TICK_RATE_MAIN_LOOP);
 
                 loop {
-
-                    settings.update();
-                    if settings.timer_mode.changed() {
-                        settings.load_default_settings_for_mode();
+                    let settings_map = asr::settings::Map::load();
+                    settings.update_from(&settings_map);
+                    apply_settings(&mut settings, &mut status);
+
+                    let transition_rules = settings_map
+                        .get(TRANSITION_RULES_KEY)
+                        .and_then(|value| value.get_array_string::<TRANSITION_RULES_MAX_LENGTH>());
+                    let transition_rules_text = match &transition_rules {
+                        Some(Ok(rules)) => rules.as_str(),
+                        Some(Err(_)) => {
+                            if !transition_rules_too_long {
+                                logging::warn(&format!(
+                                    "The custom room transition rules are longer than {TRANSITION_RULES_MAX_LENGTH} bytes, they are ignored"
+                                ));
+                            }
+                            ""
+                        }
+                        None => "",
+                    };
+                    transition_rules_too_long = matches!(transition_rules, Some(Err(_)));
+                    match split_engine.update_transition_rules(transition_rules_text) {
+                        Some(Ok(rules_count)) => logging::info(&format!(
+                            "Loaded {rules_count} custom room transition rules"
+                        )),
+                        Some(Err(err)) => logging::warn(&err),
+                        None => {}
                     }
 
-                    if let Err(text) = refresh_mem_values(&process, &mem_addresses, &mut mem_values) {
-                        print_message(text);
-                        print_message("Exiting main loop and retrying...");
-                        break;
+                    // the buffer can show up after the first scan, and stale addresses are looked for again.
+                    // it scans a little every tick, the split engine keeps running with the old values meanwhile
+                    if mem_addresses.buffer_helper.is_none() || stale_buffer {
+                        match buffer_rescan.step(
+                            &process,
+                            &mem_addresses,
+                            mem_values.room_id.current,
+                        ) {
+                            RescanStep::Found(address) => {
+                                mem_addresses.buffer_helper = Some(address);
+                                stale_buffer = false;
+                                buffer_gone = false;
+                                memory_watchdog = MemoryWatchdog::default();
+                                status.set_stage(ConnectionStage::ReadyBuffer);
+                                if let Some(line) = trace_recorder.buffer_changed(true) {
+                                    print_message(&line);
+                                }
+                            }
+                            RescanStep::NotFound if stale_buffer => buffer_gone = true,
+                            RescanStep::NotFound | RescanStep::Pending => {}
+                        }
                     }
 
-                    igt_file_secs_calculated.old = igt_file_secs_calculated.current;
-                    igt_file_secs_calculated.current = mem_values.file_minutes.current * 60.0 + mem_values.file_seconds.current;
-                    igt_level_secs_calculated.old =  igt_level_secs_calculated.current;
-                    igt_level_secs_calculated.current = mem_values.level_minutes.current * 60.0 + mem_values.level_seconds.current;
+                    // a buffer that wasn't found again gives way to the room names array, once there's one
+                    if buffer_gone && !stale_room_names {
+                        if mem_addresses.room_names.is_some() {
+                            logging::warn("The buffer is gone, continuing with the room names array.");
+                            mem_addresses.buffer_helper = None;
+                            stale_buffer = false;
+                            buffer_gone = false;
+                            memory_watchdog = MemoryWatchdog::default();
+                            status.set_stage(ConnectionStage::ReadyFallback);
+                            if let Some(line) = trace_recorder.buffer_changed(false) {
+                                print_message(&line);
+                            }
+                        } else {
+                            // the room names array wasn't needed until now
+                            stale_room_names = true;
+                            room_names_rescan.start_now();
+                        }
+                    }
 
-                    // update current level and enable full game splits
-                    if mem_values.room_name.changed() {
-                        current_level = rooms_ids::get_current_level(&mem_values.room_name.current, current_level);
-                        if !enable_full_game_split {
-                            enable_full_game_split = rooms_ids::full_game_split_unlock_rooms(&mem_values.room_name.current);
+                    if stale_room_names {
+                        match room_names_rescan.step(
+                            &process,
+                            &mem_addresses,
+                            mem_values.room_id.current,
+                        ) {
+                            RescanStep::Found(address) => {
+                                mem_addresses.room_names = Some(address);
+                                stale_room_names = false;
+                                memory_watchdog = MemoryWatchdog::default();
+                            }
+                            // the old array is kept, it's still the best there is
+                            RescanStep::NotFound => status.report_error(&MemoryError::NotFound {
+                                what: MemoryTarget::RoomNamesArray,
+                            }),
+                            RescanStep::Pending => {}
                         }
                     }
-                    timer::set_variable("Current Level", &format!("{:?}", current_level));
 
-                    // offsets for ng+ and iw
-                    if timer::state() == TimerState::NotRunning {
-                        // ng+ offset update
-                        if ng_plus_offset_seconds.is_none() && mem_values.room_name.current == "tower_entrancehall" && mem_values.level_minutes.current == 0.0 && mem_values.level_seconds.current < 1.0 {
-                            ng_plus_offset_seconds = Some(igt_file_secs_calculated.current);
+                    // the addresses can still be read after the game moves things, but the values stop making sense
+                    let stale = match refresh_mem_values(&process, &mem_addresses, &mut mem_values)
+                    {
+                        Ok(()) => {
+                            failed_reads = 0;
+                            memory_watchdog
+                                .check(&mem_values, mem_addresses.buffer_helper.is_some())
                         }
-                        if ng_plus_offset_seconds.is_some() && (mem_values.room_name.current == "hub_loadingscreen" || mem_values.room_name.current == "Finalintro") {
-                            ng_plus_offset_seconds = None;
+                        Err(err) => {
+                            status.report_error(&err);
+                            match err.recovery() {
+                                Recovery::RetryRead if failed_reads < READ_RETRY_TICKS => {
+                                    failed_reads += 1;
+                                    next_tick().await;
+                                    continue;
+                                }
+                                Recovery::Rescan(stale) => Some(stale),
+                                Recovery::RetryRead | Recovery::Detach => {
+                                    logging::warn("Exiting main loop and retrying...");
+                                    if let Some(line) = trace_recorder.finish() {
+                                        print_message(&line);
+                                    }
+                                    break;
+                                }
+                            }
                         }
-
-                        // iw offset update
-                        if iw_offset_seconds.is_none() && current_level == rooms_ids::Level::Hub {
-                            iw_offset_seconds = Some(igt_file_secs_calculated.current);
+                    };
+
+                    // the fields that failed keep their last values, so the engine still runs this tick
+                    match stale {
+                        Some(StaleMemory::Buffer) if !stale_buffer => {
+                            logging::warn("The buffer stopped making sense, looking for it again...");
+                            stale_buffer = true;
+                            buffer_rescan.start_now();
                         }
-                        if iw_offset_seconds.is_some() && current_level != rooms_ids::Level::Hub {
-                            iw_offset_seconds = None;
+                        Some(StaleMemory::RoomNames) if !stale_room_names => {
+                            logging::warn(
+                                "The room names stopped making sense, looking for the array again...",
+                            );
+                            stale_room_names = true;
+                            room_names_rescan.start_now();
                         }
+                        _ => {}
                     }
 
-                    // game time set
-                    if mem_addresses.buffer_helper.is_some() {
-                        // makes the livesplit game time frozen, if not used it stutters when the igt stops advancing
-                        timer::pause_game_time();
-
-                        let game_time_livesplit = match settings.timer_mode.current {
-                            TimerMode::FullGame => igt_file_secs_calculated.current,
-                            TimerMode::IL => igt_level_secs_calculated.current,
-                            TimerMode::NewGamePlus => igt_file_secs_calculated.current - ng_plus_offset_seconds.unwrap_or(0.0),
-                            TimerMode::IW => igt_level_secs_calculated.current - iw_offset_seconds.unwrap_or(0.0),
-                        };
-                        timer::set_game_time(Duration::seconds_f64(game_time_livesplit));
+                    if mem_values.room_name.changed() {
+                        logging::trace(&format!(
+                            "Room: {} -> {}",
+                            mem_values.room_name.old, mem_values.room_name.current
+                        ));
                     }
 
-                    // start
-                    if settings.start_enable {
-                        if settings.start_new_file && mem_values.room_name.current == "tower_entrancehall" && mem_values.room_name.old == "Finalintro" {
-                            timer::start();
+                    // trace lines are what the runner asked for, they skip the log levels
+                    if record_trace && settings.debug_record_trace {
+                        if !trace_recorder.is_recording() {
+                            print_message(&trace::header(
+                                &settings,
+                                split_engine.transition_rules_text(),
+                                mem_addresses.buffer_helper.is_some(),
+                            ));
                         }
-                        if settings.start_any_file && mem_values.room_name.current == "tower_entrancehall" && mem_values.room_name.old == "hub_loadingscreen" {
-                            timer::start();
+                        if let Some(line) = trace_recorder.record(&mem_values) {
+                            print_message(&line);
                         }
-                        if settings.start_new_il && rooms_ids::get_starting_room(&current_level) == mem_values.room_name.current && igt_level_secs_calculated.current > 0.07 && igt_level_secs_calculated.current <= 0.1 {
-                            timer::start();
-                        }
-                        if settings.start_exit_level && mem_values.room_name.changed() && rooms_ids::full_game_split_rooms(&mem_values.room_name.old) && current_level == Level::Hub {
-                            timer::start();
+                    } else if trace_recorder.is_recording() {
+                        record_trace = false;
+                        if let Some(line) = trace_recorder.finish() {
+                            print_message(&line);
                         }
                     }
 
-                    // reset
-                    if settings.reset_enable {
-                        if settings.reset_new_file && mem_values.room_name.current == "Finalintro" && mem_values.room_name.old != "Finalintro" {
-                            timer::reset();
-                        }
-                        if settings.reset_any_file && mem_values.room_name.changed() && mem_values.room_name.current == "hub_loadingscreen" {
-                            timer::reset();
-                        }
-                        if settings.reset_new_level && igt_level_secs_calculated.decreased() && current_level != Level::Hub {
-                            timer::reset();
-                        }
+                    let actions = split_engine.update(
+                        &mem_values,
+                        &settings,
+                        timer::state(),
+                        mem_addresses.buffer_helper.is_some(),
+                    );
+                    variables::set(
+                        "Current Level",
+                        format_args!("{:?}", split_engine.current_level()),
+                    );
+                    if mem_addresses.buffer_helper.is_some() {
+                        variables::set(
+                            "Level Time",
+                            format_args!(
+                                "{}:{:06.3}",
+                                mem_values.level_minutes.current, mem_values.level_seconds.current
+                            ),
+                        );
+                        variables::set(
+                            "File Time",
+                            format_args!(
+                                "{}:{:06.3}",
+                                mem_values.file_minutes.current, mem_values.file_seconds.current
+                            ),
+                        );
+                    }
+                    status.set_addresses(&mem_addresses, settings.fallback_load_removal);
+                    status.set_game_version(&mem_values.game_version.current);
+                    status.set_last_split(split_engine.last_split_reason());
+                    if mem_values.room_name.changed() {
+                        let room_name = mem_values.room_name.current.as_str();
+                        variables::set(
+                            "Unknown Room",
+                            if rooms_ids::is_known_room(room_name) {
+                                "-"
+                            } else {
+                                room_name
+                            },
+                        );
                     }
 
-                    // split
-                    if settings.splits_enable {
-
-                        // covers any full game split
-                        if settings.splits_level_end {
-
-                            // standard level / boss end
-                            // got lazy and hardcoded the noise pizzaface split here :)
-                            if mem_values.room_name.changed()
-                            && rooms_ids::full_game_split_rooms(&mem_values.room_name.old)
-                            && (current_level == Level::Hub || current_level == Level::ResultsScreen)
-                            && enable_full_game_split
-                            && (mem_values.boss_hp.old == 0 || (mem_values.room_name.current == "boss_pizzafacehub" && mem_values.room_name.old == "boss_pizzaface")) {
-                                timer::split();
-                                enable_full_game_split = false;
-                            }
-
-                            // end of the run frame perfect split, technically the prev "if" could cover this too but frame perfectly splitting at the end is cooler
-                            if mem_values.end_of_level.current && !mem_values.end_of_level.old && mem_values.room_name.current == "tower_entrancehall" {
-                                timer::split();
-                            }
-
-                            // ctop entering from oob
-                            if timer::state() == TimerState::NotRunning && ctop_oob_split {
-                                ctop_oob_split = false;
+                    for action in actions {
+                        match action {
+                            TimerAction::Start => timer::start(),
+                            TimerAction::Split => timer::split(),
+                            TimerAction::Reset => timer::reset(),
+                            TimerAction::PauseGameTime => timer::pause_game_time(),
+                            TimerAction::ResumeGameTime => timer::resume_game_time(),
+                            TimerAction::SetGameTime(seconds) => {
+                                timer::set_game_time(Duration::seconds_f64(seconds))
                             }
-                            if mem_values.room_name.current == "tower_finalhallway" && mem_values.room_name.old == "tower_5" && !ctop_oob_split {
-                                ctop_oob_split = true;
-                                timer::split();
-                            }
-                        }
-
-                        if settings.splits_rooms
-                        && (igt_level_secs_calculated.current - last_room_split_time > 2.0 || mem_values.room_name.current != last_room_split_name)
-                        && (mem_values.room_name.changed() || mem_values.end_of_level.current && mem_values.end_of_level.old) {
-                            last_room_split_time = igt_level_secs_calculated.current;
-                            last_room_split_name = mem_values.room_name.old.clone();
-
-                            asr::timer::split();
                         }
-
                     }
 
                     next_tick().await;
                 }
-            } else {
-                asr::set_tick_rate(TICK_RATE_INIT);
-            }
-        }).await;
+            })
+            .await;
+
+        status.set_stage(ConnectionStage::Lost);
+        status.set_addresses(&MemoryAddresses::default(), settings.fallback_load_removal);
+        asr::set_tick_rate(TICK_RATE_INIT);
     }
 }
@@ -0,0 +1,107 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+#[cfg(target_arch = "wasm32")]
+use asr::print_message;
+
+use crate::settings::LogLevel;
+
+impl LogLevel {
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        }
+    }
+}
+
+/**
+ * the tests have no LiveSplit log, they print to stderr
+ */
+#[cfg(not(target_arch = "wasm32"))]
+fn print_message(line: &str) {
+    eprintln!("{line}");
+}
+
+// lines kept for dump(), enough for the whole connection to the game and a few errors after it
+const HISTORY_LINES: usize = 200;
+
+struct Logger {
+    level: LogLevel,
+    history: VecDeque<String>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    level: LogLevel::Info,
+    history: VecDeque::new(),
+});
+
+#[cfg(target_arch = "wasm32")]
+pub fn set_level(level: LogLevel) {
+    if let Ok(mut logger) = LOGGER.lock() {
+        logger.level = level;
+    }
+}
+
+/**
+ * Prints the message if the level is picked. Debug messages are always kept for dump(), even when they aren't printed
+ */
+pub fn log(level: LogLevel, message: &str) {
+    let Ok(mut logger) = LOGGER.lock() else {
+        return;
+    };
+    if level > logger.level.max(LogLevel::Debug) {
+        return;
+    }
+
+    let line = format!("[{}] {message}", level.label());
+    if level <= logger.level {
+        print_message(&line);
+    }
+    if logger.history.len() >= HISTORY_LINES {
+        logger.history.pop_front();
+    }
+    logger.history.push_back(line);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn error(message: &str) {
+    log(LogLevel::Error, message);
+}
+
+pub fn warn(message: &str) {
+    log(LogLevel::Warn, message);
+}
+
+pub fn info(message: &str) {
+    log(LogLevel::Info, message);
+}
+
+pub fn debug(message: &str) {
+    log(LogLevel::Debug, message);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn trace(message: &str) {
+    log(LogLevel::Trace, message);
+}
+
+/**
+ * prints the last lines of the log again no matter the level, for runners to paste into bug reports
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn dump() {
+    let Ok(logger) = LOGGER.lock() else {
+        return;
+    };
+    print_message(&format!(
+        "--- last {} lines of the log ---",
+        logger.history.len()
+    ));
+    for line in &logger.history {
+        print_message(line);
+    }
+    print_message("--- end of the log ---");
+}
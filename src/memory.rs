@@ -1,310 +1,871 @@
-use crate::{MemoryAddresses, MemoryValues};
-use asr::{signature::Signature, watcher::Pair, Address, Process};
+use crate::{
+    address_cache,
+    game_memory::{GameMemory, SignaturePattern},
+    logging,
+    memory_error::{MemoryError, MemoryTarget},
+    memory_fields::{BufferRead, MemoryValues, MAX_STRING_LENGTH},
+    rooms_ids, speedrun_buffer, variables, MemoryAddresses,
+};
+use asr::{future::next_tick, signature::Signature, Address};
+// the tests have no runtime to ask for the time
+#[cfg(target_arch = "wasm32")]
+use asr::time_util::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
-// the array with all the room names
-const ROOM_ID_ARRAY_SIG: Signature<13> = Signature::new("74 0C 48 8B 05 ?? ?? ?? ?? 48 8B 04 D0");
-// the id of the current room the player is on (i32)
-const ROOM_ID_SIG: Signature<9> = Signature::new("89 3D ?? ?? ?? ?? 48 3B 1D");
+/**
+ * How the address of a target is found from a signature match
+ */
+enum AddressRule {
+    /// the match is the target
+    Match,
+    /// the match is an instruction with a rip relative u32 at `displacement`, relative to the end of the instruction
+    RipRelative {
+        displacement: u64,
+        instruction_end: u64,
+    },
+}
+
+/**
+ * One of the signatures that can find a target, game patches can change the code around it so a target can have a few of them.
+ * Variants of the same target can have different lengths
+ */
+struct SignatureVariant {
+    name: &'static str,
+    signature: &'static dyn SignaturePattern,
+    rule: AddressRule,
+}
+
+impl SignatureVariant {
+    fn resolve<M: GameMemory>(&self, memory: &M, add: Address) -> Option<Address> {
+        match self.rule {
+            AddressRule::Match => Some(add),
+            AddressRule::RipRelative {
+                displacement,
+                instruction_end,
+            } => {
+                let offset = memory
+                    .read_u32(Address::new(add.value() + displacement))
+                    .ok()?;
+                Some(Address::new(add.value() + instruction_end + offset as u64))
+            }
+        }
+    }
+}
+
+// the id of the current room the player is on (i32), tried in order.
+// only one signature is known for it, other versions of the game may need another one here
+const ROOM_ID_SIGS: [SignatureVariant; 1] = [SignatureVariant {
+    name: "mov room id, cmp",
+    signature: &Signature::<9>::new("89 3D ?? ?? ?? ?? 48 3B 1D"),
+    rule: AddressRule::RipRelative {
+        displacement: 0x2,
+        instruction_end: 0x6,
+    },
+}];
+
+// the pointer to the array with all the room names, tried in order
+const ROOM_NAMES_ARRAY_SIGS: [SignatureVariant; 2] = [
+    SignatureVariant {
+        name: "je, mov array, mov name",
+        signature: &Signature::<13>::new("74 0C 48 8B 05 ?? ?? ?? ?? 48 8B 04 D0"),
+        rule: AddressRule::RipRelative {
+            displacement: 0x5,
+            instruction_end: 0x9,
+        },
+    },
+    // without the jump before it, in case the branch around the array read changes
+    SignatureVariant {
+        name: "mov array, mov name",
+        signature: &Signature::<11>::new("48 8B 05 ?? ?? ?? ?? 48 8B 04 D0"),
+        rule: AddressRule::RipRelative {
+            displacement: 0x3,
+            instruction_end: 0x7,
+        },
+    },
+];
 
 // the magic numbers to find for the buffer
-// the full 32 numbers didn't work for some reason... so we use 16 of them
-const BUFFER_MAGIC_NUMBER: Signature<16> =
-    Signature::new("C2 5A 17 65 BE 4D DF D6 F2 1C D1 3B A7 A6 1F C3");
+// the full 32 numbers didn't work for some reason... so we use 16 of them, the same as speedrun_buffer::MAGIC_PREFIX.
+// every match is checked with is_valid_buffer since copies of these bytes can be anywhere in the heap.
+// only these are known, a game version that changes them needs another variant here
+const BUFFER_SIGS: [SignatureVariant; 1] = [SignatureVariant {
+    name: "magic numbers",
+    signature: &Signature::<16>::new("C2 5A 17 65 BE 4D DF D6 F2 1C D1 3B A7 A6 1F C3"),
+    rule: AddressRule::Match,
+}];
+
+// bytes scanned before yielding to the runtime, so livesplit doesn't freeze while scanning a big heap
+const SCAN_BYTES_PER_TICK: u64 = 0x100_0000;
 
 /**
- * update a pair and display it in the variable view of livesplit
+ * how far a signature scan is, shown in the "Scan Progress" variable
  */
-fn update_pair<T: core::fmt::Display + Copy>(
-    variable_name: &str,
-    new_value: T,
-    pair: &mut Pair<T>,
-) {
-    asr::timer::set_variable(variable_name, &format!("{new_value}"));
-    pair.old = pair.current;
-    pair.current = new_value;
+struct ScanProgress<'a> {
+    target: &'a str,
+    ranges_scanned: usize,
+    ranges_total: usize,
+    bytes_scanned: u64,
+    // the tick rate changes while attached so ticks don't tell the time
+    started: Instant,
+}
+
+impl ScanProgress<'_> {
+    fn publish(&self) {
+        variables::set(
+            "Scan Progress",
+            format_args!(
+                "{}: {}/{} ranges, {:.1} MiB, {:.1}s",
+                self.target,
+                self.ranges_scanned,
+                self.ranges_total,
+                self.bytes_scanned as f64 / (1024.0 * 1024.0),
+                self.started.elapsed().as_secs_f64(),
+            ),
+        );
+    }
 }
 
 /**
- * reads a UTF-8 string from memory to update a Pair<String>, if it fails the Pair is left intact, if successful also displays it in the livesplit variable viewer
+ * scans at most max_bytes of the range from the offset, returns the match if is_valid takes it and how many bytes it covered
  */
-fn read_string_and_update_pair(
-    process: &Process,
-    main_module_addr: asr::Address,
-    pointer_path: &[u64],
-    variable_name: &str,
-    pair: &mut Pair<String>,
-) {
-    let buf = match process.read_pointer_path::<[u8; 100]>(
-        main_module_addr.value(),
-        asr::PointerSize::Bit64,
-        pointer_path,
-    ) {
-        Ok(bytes) => bytes.to_vec(),
-        Err(_) => return,
-    };
+fn scan_chunk<M: GameMemory>(
+    memory: &M,
+    signature: &dyn SignaturePattern,
+    (range_start, range_size): (Address, u64),
+    offset: u64,
+    max_bytes: u64,
+    is_valid: &impl Fn(Address) -> bool,
+) -> (Option<Address>, u64) {
+    let chunk_size = (range_size - offset).min(max_bytes);
+    // overlap the next chunk so a signature between two of them isn't missed
+    let scanned_size = (chunk_size + signature.length() as u64 - 1).min(range_size - offset);
+    let chunk_start = Address::new(range_start.value() + offset);
+
+    match memory.scan(signature, (chunk_start, scanned_size)) {
+        Some(address) if is_valid(address) => (Some(address), chunk_size),
+        // go on right after a rejected match
+        Some(address) => (None, address.value() + 1 - chunk_start.value()),
+        None => (None, chunk_size),
+    }
+}
 
-    let string_as_bytes = if let Some(array) = buf.split(|byte| *byte == 0).next() {
-        array.to_vec()
-    } else {
-        return;
+/**
+ * Scans the ranges in order for the signature, scanning at most SCAN_BYTES_PER_TICK bytes before waiting for the next tick.
+ * Matches that is_valid rejects are skipped and the scan goes on right after them
+ */
+async fn scan_ranges<M: GameMemory>(
+    memory: &M,
+    signature: &dyn SignaturePattern,
+    ranges: &[(Address, u64)],
+    target: &str,
+    is_valid: impl Fn(Address) -> bool,
+) -> Option<Address> {
+    let mut progress = ScanProgress {
+        target,
+        ranges_scanned: 0,
+        ranges_total: ranges.len(),
+        bytes_scanned: 0,
+        started: Instant::now(),
     };
+    let mut tick_budget = SCAN_BYTES_PER_TICK;
+
+    for range in ranges {
+        let mut offset = 0;
+        while offset < range.1 {
+            let (found, scanned_bytes) =
+                scan_chunk(memory, signature, *range, offset, tick_budget, &is_valid);
+            if found.is_some() {
+                progress.publish();
+                return found;
+            }
 
-    let parsed_string;
-    if let Ok(string) = String::from_utf8(string_as_bytes) {
-        parsed_string = string.splitn(2, '\0').collect::<String>();
-    } else {
-        return;
+            offset += scanned_bytes;
+            progress.bytes_scanned += scanned_bytes;
+            tick_budget = tick_budget.saturating_sub(scanned_bytes);
+            if tick_budget == 0 {
+                progress.publish();
+                next_tick().await;
+                tick_budget = SCAN_BYTES_PER_TICK;
+            }
+        }
+        progress.ranges_scanned += 1;
+    }
+
+    progress.publish();
+    None
+}
+
+/**
+ * Tries the variants in order and returns the target address from the first one that finds a valid target.
+ * The variant that won is shown in the "<target> Signature" variable
+ */
+async fn scan_variants<M: GameMemory>(
+    memory: &M,
+    variants: &[SignatureVariant],
+    ranges: &[(Address, u64)],
+    target: &str,
+    is_valid: impl Fn(Address) -> bool,
+) -> Option<Address> {
+    for variant in variants {
+        let found = scan_ranges(memory, variant.signature, ranges, target, |add| {
+            variant.resolve(memory, add).is_some_and(&is_valid)
+        })
+        .await
+        .and_then(|add| variant.resolve(memory, add));
+
+        if let Some(address) = found {
+            variables::set_debug(&format!("{target} Signature"), variant.name);
+            return Some(address);
+        }
     }
 
-    asr::timer::set_variable(variable_name, &parsed_string);
-    pair.old = pair.current.clone();
-    pair.current = parsed_string;
+    variables::set_debug(&format!("{target} Signature"), "none");
+    None
 }
 
-pub fn room_id_sigscan_start(
-    process: &asr::Process,
+/**
+ * the code signatures are only searched in the image of PizzaTower.exe, the heap can have copies of the same bytes
+ */
+fn main_module_range(addresses: &MemoryAddresses) -> Option<(Address, u64)> {
+    Some((addresses.main_address?, addresses.main_module_size?))
+}
+
+// room ids past this are garbage, the game has less than a thousand rooms
+const MAX_ROOM_ID: i32 = 0x2000;
+
+/**
+ * the room id is 0 while the game opens, anything else has to be a possible room
+ */
+fn is_plausible_room_id<M: GameMemory>(memory: &M, room_id_address: Address) -> bool {
+    memory
+        .read_i32(room_id_address)
+        .is_ok_and(|room_id| (0..MAX_ROOM_ID).contains(&room_id))
+}
+
+// room ids past the end of the array are read as more names, this many empty entries in a row is the end
+const MAX_EMPTY_ROOM_NAMES: i32 = 0x10;
+
+/**
+ * Walks the names of an array up to the first thing that isn't a room name and returns how many rooms it has.
+ * None unless one of rooms_ids::ANCHOR_ROOMS is in it, any pointer to pointers to strings would pass otherwise
+ */
+fn rooms_array_length<M: GameMemory>(memory: &M, rooms_array: Address) -> Option<i32> {
+    let mut buf = [0; MAX_STRING_LENGTH];
+    let mut length = 0;
+    let mut empty_names = 0;
+    let mut has_anchor = false;
+
+    for room_id in 0..MAX_ROOM_ID {
+        let Ok(name_address) =
+            memory.read_u64(Address::new(rooms_array.value() + room_id as u64 * 0x8))
+        else {
+            break;
+        };
+        // rooms removed from the game leave holes
+        if name_address == 0 {
+            empty_names += 1;
+            if empty_names >= MAX_EMPTY_ROOM_NAMES {
+                break;
+            }
+            continue;
+        }
+        let Ok(name) = memory.read_c_string(Address::new(name_address), &mut buf) else {
+            break;
+        };
+        if !rooms_ids::is_valid_room_name(name) {
+            break;
+        }
+        has_anchor |= rooms_ids::ANCHOR_ROOMS.contains(&name);
+        empty_names = 0;
+        length = room_id + 1;
+    }
+
+    has_anchor.then_some(length)
+}
+
+/**
+ * The array is only taken if it has one of the rooms every version has and the current room id is inside it
+ */
+fn is_plausible_rooms_array<M: GameMemory>(
+    memory: &M,
+    pointer_to_rooms_array: Address,
+    room_id: i32,
+) -> bool {
+    if room_id < 0 {
+        return false;
+    }
+    match memory.read_u64(pointer_to_rooms_array) {
+        Ok(rooms_array) if rooms_array != 0 => {
+            rooms_array_length(memory, Address::new(rooms_array))
+                .is_some_and(|length| room_id < length)
+        }
+        _ => false,
+    }
+}
+
+pub async fn room_id_sigscan_start<M: GameMemory>(
+    memory: &M,
     addresses: MemoryAddresses,
-) -> Result<asr::Address, ()> {
+) -> Result<asr::Address, MemoryError> {
     let main_address = addresses.main_address.unwrap_or(Address::new(0));
 
-    // room id sigscan
-    asr::print_message("Starting the room id signature scan...");
-    let mut room_id_address: Option<Address> = None;
-    for range in process.memory_ranges().rev() {
-        let address = range.address().unwrap().value();
-        let size = range.size().unwrap_or_default();
-
-        if let Some(add) = ROOM_ID_SIG.scan_process_range(process, (address, size)) {
-            let offset = match process.read::<u32>(Address::new(add.value() + 0x2)) {
-                Ok(offset) => offset,
-                Err(_) => {
-                    asr::print_message("Could not find offset for room id");
-                    return Err(());
-                }
-            };
-            room_id_address = Some(Address::new(
-                add.value() + 0x6 + offset as u64 - main_address.value(),
-            ));
-            break;
+    let Some(module_range) = main_module_range(&addresses) else {
+        return Err(MemoryError::NotFound {
+            what: MemoryTarget::MainModule,
+        });
+    };
+
+    // a scan of the same build of the game already found it
+    let module_id = address_cache::module_identity(memory, module_range.0, module_range.1);
+    if let Some(offset) = module_id
+        .as_deref()
+        .and_then(|module_id| address_cache::load_offset(module_id, address_cache::ROOM_ID_KEY))
+    {
+        if is_plausible_room_id(memory, Address::new(main_address.value() + offset)) {
+            variables::set_debug("Room Id Address", format_args!("{offset:X}"));
+            variables::set_debug("Room ID Signature", "cached");
+            logging::debug("Room ID address loaded from the cache.");
+            return Ok(Address::new(offset));
         }
+        logging::info("The cached room ID address isn't valid, scanning again...");
     }
 
+    // room id sigscan, it's code so only the module image has it
+    logging::debug("Starting the room id signature scan...");
+    let room_id_address = scan_variants(
+        memory,
+        &ROOM_ID_SIGS,
+        &[module_range],
+        "Room ID",
+        |address| is_plausible_room_id(memory, address),
+    )
+    .await
+    .map(|address| Address::new(address.value() - main_address.value()));
+
     match room_id_address {
         Some(address) => {
-            asr::timer::set_variable(
-                "Room Id Address",
-                &format!("{:X}", room_id_address.unwrap().value()),
-            );
-            asr::print_message("Room ID signature scan complete.");
+            variables::set_debug("Room Id Address", format_args!("{:X}", address.value()));
+            logging::info("Room ID signature scan complete.");
+            if let Some(module_id) = &module_id {
+                address_cache::store_offset(module_id, address_cache::ROOM_ID_KEY, address.value());
+            }
             Ok(address)
         }
-        None => {
-            asr::print_message("Could NOT complete the room ID scan.");
-            Err(())
-        }
+        None => Err(MemoryError::NotFound {
+            what: MemoryTarget::RoomId,
+        }),
     }
 }
 
-pub fn room_name_array_sigscan_start(process: &asr::Process) -> Result<asr::Address, &str> {
-    asr::print_message("Starting the name array signature scan...");
-    let mut pointer_to_rooms_array: Option<Address> = None;
-    // get pointer scan add -> read u32 5 bytes after the result to find offset -> result is add scanned + 9 + offset
-    for range in process.memory_ranges().rev() {
-        let address = range.address().unwrap_or_default().value();
-        let size = range.size().unwrap_or_default();
-
-        if let Some(add) = ROOM_ID_ARRAY_SIG.scan_process_range(process, (address, size)) {
-            let offset = match process.read::<u32>(Address::new(add.value() + 0x5)) {
-                Ok(pointer) => pointer,
-                Err(_) => return Err("Could not read offset to find the room names array"),
-            };
-            pointer_to_rooms_array = Some(Address::new(add.value() + 0x9 + offset as u64));
-            break;
-        };
+/**
+ * finds the array with the names of all the rooms, the current room id is used to check that the array is the right one
+ */
+pub async fn room_name_array_sigscan_start<M: GameMemory>(
+    memory: &M,
+    addresses: &MemoryAddresses,
+    room_id: i32,
+) -> Result<asr::Address, MemoryError> {
+    let Some(module_range) = main_module_range(addresses) else {
+        return Err(MemoryError::NotFound {
+            what: MemoryTarget::MainModule,
+        });
+    };
+
+    // a scan of the same build of the game already found the pointer to the array
+    let module_id = address_cache::module_identity(memory, module_range.0, module_range.1);
+    if let Some(offset) = module_id.as_deref().and_then(|module_id| {
+        address_cache::load_offset(module_id, address_cache::ROOM_NAMES_POINTER_KEY)
+    }) {
+        let pointer_to_rooms_array = Address::new(module_range.0.value() + offset);
+        if is_plausible_rooms_array(memory, pointer_to_rooms_array, room_id) {
+            if let Ok(add) = memory.read_u64(pointer_to_rooms_array) {
+                logging::debug("Room name array pointer loaded from the cache.");
+                variables::set_debug("Room names array Signature", "cached");
+                variables::set_debug(
+                    "Room names array",
+                    format_args!("{:X}", pointer_to_rooms_array.value()),
+                );
+                return Ok(Address::new(add));
+            }
+        }
+        logging::info("The cached room names array pointer isn't valid, scanning again...");
     }
 
+    logging::debug("Starting the name array signature scan...");
+    let pointer_to_rooms_array = scan_variants(
+        memory,
+        &ROOM_NAMES_ARRAY_SIGS,
+        &[module_range],
+        "Room names array",
+        |pointer| is_plausible_rooms_array(memory, pointer, room_id),
+    )
+    .await;
+
     match pointer_to_rooms_array {
-        Some(address) => match process.read::<u64>(address) {
+        Some(address) => match memory.read_u64(address) {
             Ok(add) => {
-                asr::print_message("Room name array signature scan complete.");
-                asr::timer::set_variable("Room names array", &format!("{:X}", address.value()));
+                logging::info("Room name array signature scan complete.");
+                variables::set_debug("Room names array", format_args!("{:X}", address.value()));
+                if let Some(module_id) = &module_id {
+                    address_cache::store_offset(
+                        module_id,
+                        address_cache::ROOM_NAMES_POINTER_KEY,
+                        address.value() - module_range.0.value(),
+                    );
+                }
                 Ok(Address::new(add))
             }
-            Err(_) => Err("Could not read the array address"),
+            Err(_) => Err(MemoryError::ReadFailed {
+                what: MemoryTarget::RoomNamesArray,
+                address,
+            }),
         },
-        None => Err("Could not find signature for room names array"),
+        None => Err(MemoryError::NotFound {
+            what: MemoryTarget::RoomNamesArray,
+        }),
     }
 }
 
-pub fn buffer_helper_sigscan_init(process: &asr::Process) -> Result<asr::Address, ()> {
-    asr::print_message("Starting the helper buffer signature scan...");
-
-    let mut helper_address: Option<Address> = None;
+/**
+ * A match of the magic numbers is only taken if the rest of the buffer makes sense: a printable version string
+ * and in game times that are real numbers
+ */
+fn is_valid_buffer<M: GameMemory>(memory: &M, address: Address) -> bool {
+    memory
+        .read_array::<{ MemoryValues::BUFFER_SIZE }>(address)
+        .is_ok_and(|buffer_bytes| speedrun_buffer::is_plausible(&buffer_bytes))
+}
 
-    for range in process.memory_ranges() {
-        let address = range.address().unwrap_or_default().value();
-        let size = range.size().unwrap_or_default();
-        if let Some(address) = BUFFER_MAGIC_NUMBER.scan_process_range(process, (address, size)) {
-            helper_address = Some(address);
-            break;
-        }
-    }
+pub async fn buffer_helper_sigscan_init<M: GameMemory>(
+    memory: &M,
+) -> Result<asr::Address, MemoryError> {
+    logging::debug("Starting the helper buffer signature scan...");
+
+    // the buffer is allocated by the game, so unlike the code signatures it can be anywhere in the heap
+    let helper_address = scan_variants(
+        memory,
+        &BUFFER_SIGS,
+        &memory.memory_ranges(),
+        "Buffer",
+        |address| is_valid_buffer(memory, address),
+    )
+    .await;
 
     // this is a direct reference to the speedrun data, finding the scanned address is enough
     if let Some(add) = helper_address {
-        asr::timer::set_variable(
-            "Buffer address",
-            &format!("{:X}", helper_address.unwrap_or(Address::new(0)).value()),
-        );
-        asr::print_message("Buffer sigscan complete");
+        variables::set_debug("Buffer address", format_args!("{:X}", add.value()));
+        logging::info("Buffer sigscan complete");
         Ok(add)
     } else {
-        asr::print_message("Could not complete the buffer helper sigscan. Is the \"-livesplit\" launch option set?");
-        asr::print_message("Continuing with the basic real time and split features.");
-        Err(())
+        logging::warn("Could not complete the buffer helper sigscan. Is the \"-livesplit\" launch option set?");
+        logging::warn(
+            "Continuing with the basic real time and split features, it will keep looking for the buffer.",
+        );
+        Err(MemoryError::NotFound {
+            what: MemoryTarget::Buffer,
+        })
     }
 }
 
-pub fn refresh_mem_values<'a>(
-    process: &'a Process,
-    memory_addresses: &'a MemoryAddresses,
-    memory_values: &mut MemoryValues,
-) -> Result<(), &'a str> {
-    let main_address;
-    if let Some(address) = memory_addresses.main_address {
-        main_address = address;
-    } else {
-        return Err("Main Address is None in refresh mem values function");
+// wait between two passes of a rescan that found nothing, 5 seconds at the main loop tick rate
+const RESCAN_INTERVAL_TICKS: u32 = 240 * 5;
+// the main loop runs 240 times a second, so this is under 16 MiB a second. A pass of a big heap takes a minute or two,
+// which is fine for something the splitter does in the background
+const RESCAN_BYTES_PER_TICK: u64 = 0x1_0000;
+
+/**
+ * What a step of a rescan did
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RescanStep {
+    /// waiting for the next pass or in the middle of one
+    Pending,
+    Found(Address),
+    /// a whole pass found nothing, the next one starts after RESCAN_INTERVAL_TICKS
+    NotFound,
+}
+
+/**
+ * Looks for the buffer or the room names array again while the main loop keeps running, it scans a little on every tick
+ * so the splitter never stops while it looks. The buffer is looked for every few seconds in fallback mode since the game
+ * may not have allocated it when the first scan ran, and both are looked for right away when the watchdog says they're stale
+ */
+pub struct Rescan {
+    target: MemoryTarget,
+    idle_ticks: u32,
+    ranges: Vec<(Address, u64)>,
+    variant: usize,
+    range_index: usize,
+    offset: u64,
+}
+
+impl Rescan {
+    pub fn new(target: MemoryTarget) -> Self {
+        Self {
+            target,
+            idle_ticks: 0,
+            ranges: Vec::new(),
+            variant: 0,
+            range_index: 0,
+            offset: 0,
+        }
     }
 
-    if let Ok(value) = process.read::<i32>(Address::new(
-        memory_addresses.room_id.unwrap_or(Address::new(0)).value() + main_address.value(),
-    )) {
-        update_pair("Room ID", value, &mut memory_values.room_id);
-    } else {
-        return Err("Could not read the room ID from memory");
-    }
-
-    // only update if buffer helper was found
-    if memory_addresses.buffer_helper.is_some() {
-        /*
-        Buffer documentation:
-        0x00: magic numbers
-        0x40: game version (string)
-        0x80: file minutes (f64)
-        0x88: file seconds (f64)
-        0x90: level minute (f64)
-        0x98: level seconds (f64)
-        0xA0: current room (string)
-        0xE0: end of level fade exists (bool / u8)
-        0xE1: boss HP (u8)
-        */
-
-        // game version doesn't need to be updated more tha once...
-        if memory_values.game_version.current == String::default() {
-            let game_version = memory_addresses
-                .buffer_helper
-                .unwrap_or(Address::new(0))
-                .value()
-                + 0x40;
-
-            read_string_and_update_pair(
-                process,
-                Address::new(0),
-                &[game_version],
-                "Game Version",
-                &mut memory_values.game_version,
-            );
+    /**
+     * the next step starts a pass without waiting, unless one is already going
+     */
+    pub fn start_now(&mut self) {
+        self.idle_ticks = RESCAN_INTERVAL_TICKS;
+    }
+
+    fn variants(&self) -> &'static [SignatureVariant] {
+        match self.target {
+            MemoryTarget::Buffer => &BUFFER_SIGS,
+            _ => &ROOM_NAMES_ARRAY_SIGS,
         }
+    }
 
-        let file_minutes_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0x80;
-        let file_seconds_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0x88;
-        let level_minutes_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0x90;
-        let level_seconds_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0x98;
-        let room_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0xA0;
-        let end_level_fade_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0xE0;
-        let boss_hp_add = memory_addresses
-            .buffer_helper
-            .unwrap_or(Address::new(0))
-            .value()
-            + 0xE1;
-
-        if let Ok(value) = process.read::<f64>(Address::new(file_seconds_add)) {
-            update_pair("File Seconds", value, &mut memory_values.file_seconds);
-        };
+    /**
+     * Scans the next part of the memory. The room id is the current one, a room names array has to have it.
+     * The address is only given back once a match validates, the main loop keeps the old one until then
+     */
+    pub fn step<M: GameMemory>(
+        &mut self,
+        memory: &M,
+        addresses: &MemoryAddresses,
+        room_id: i32,
+    ) -> RescanStep {
+        if self.ranges.is_empty() {
+            if self.idle_ticks < RESCAN_INTERVAL_TICKS {
+                self.idle_ticks += 1;
+                return RescanStep::Pending;
+            }
+            // start a new pass, the heap ranges change as the game allocates.
+            // like the first scans, the array pointer is in the code so only the module image has it
+            self.ranges = match self.target {
+                MemoryTarget::Buffer => memory.memory_ranges(),
+                _ => main_module_range(addresses).into_iter().collect(),
+            };
+            self.variant = 0;
+            self.range_index = 0;
+            self.offset = 0;
+            if self.ranges.is_empty() {
+                self.idle_ticks = 0;
+                return RescanStep::NotFound;
+            }
+        }
 
-        if let Ok(value) = process.read::<f64>(Address::new(file_minutes_add)) {
-            update_pair("File Minutes", value, &mut memory_values.file_minutes);
+        let variant = &self.variants()[self.variant];
+        let range = self.ranges[self.range_index];
+        let is_valid = |address| match self.target {
+            MemoryTarget::Buffer => is_valid_buffer(memory, address),
+            _ => is_plausible_rooms_array(memory, address, room_id),
         };
-
-        if let Ok(value) = process.read::<f64>(Address::new(level_seconds_add)) {
-            update_pair("Level Seconds", value, &mut memory_values.level_seconds);
+        let (found, scanned_bytes) = scan_chunk(
+            memory,
+            variant.signature,
+            range,
+            self.offset,
+            RESCAN_BYTES_PER_TICK,
+            &|add| variant.resolve(memory, add).is_some_and(is_valid),
+        );
+        let found = match found.and_then(|add| variant.resolve(memory, add)) {
+            Some(address) if self.target == MemoryTarget::Buffer => {
+                variables::set_debug("Buffer Signature", variant.name);
+                variables::set_debug("Buffer address", format_args!("{:X}", address.value()));
+                Some(address)
+            }
+            // the array is what the pointer points to
+            Some(pointer) => memory.read_u64(pointer).ok().map(|add| {
+                variables::set_debug("Room names array Signature", variant.name);
+                variables::set_debug("Room names array", format_args!("{:X}", pointer.value()));
+                Address::new(add)
+            }),
+            None => None,
         };
+        if let Some(address) = found {
+            *self = Self::new(self.target);
+            return RescanStep::Found(address);
+        }
 
-        if let Ok(value) = process.read::<f64>(Address::new(level_minutes_add)) {
-            update_pair("Level Minutes", value, &mut memory_values.level_minutes);
-        };
+        self.offset += scanned_bytes;
+        if self.offset >= range.1 {
+            self.offset = 0;
+            self.range_index += 1;
+            if self.range_index >= self.ranges.len() {
+                self.range_index = 0;
+                self.variant += 1;
+                if self.variant >= self.variants().len() {
+                    // nothing this pass, wait before the next one
+                    *self = Self::new(self.target);
+                    return RescanStep::NotFound;
+                }
+            }
+        }
+        RescanStep::Pending
+    }
+}
 
-        read_string_and_update_pair(
-            process,
-            Address::new(0),
-            &[room_add],
-            "Room Name (Buffer)",
-            &mut memory_values.room_name,
-        );
+pub fn refresh_mem_values<M: GameMemory>(
+    memory: &M,
+    memory_addresses: &MemoryAddresses,
+    memory_values: &mut MemoryValues,
+) -> Result<(), MemoryError> {
+    if memory_addresses.main_address.is_none() {
+        return Err(MemoryError::NotFound {
+            what: MemoryTarget::MainModule,
+        });
+    }
 
-        if let Ok(value) = process.read::<bool>(Address::new(end_level_fade_add)) {
-            update_pair("End Fade Exists", value, &mut memory_values.end_of_level);
-        };
+    // the whole buffer in one read per tick, so every value comes from the same frame of the game
+    let buffer_bytes = memory_addresses
+        .buffer_helper
+        .map(|buffer_address| memory.read_array::<{ MemoryValues::BUFFER_SIZE }>(buffer_address));
+    let buffer = match &buffer_bytes {
+        None => BufferRead::Missing,
+        Some(Ok(bytes)) if speedrun_buffer::is_plausible(bytes) => BufferRead::Valid(bytes),
+        // a buffer that can't be read or doesn't make sense anymore keeps the last values, the watchdog looks for it again
+        Some(_) => BufferRead::Invalid,
+    };
+    memory_values.buffer_valid = matches!(buffer, BufferRead::Valid(..));
 
-        if let Ok(value) = process.read::<u8>(Address::new(boss_hp_add)) {
-            update_pair("Boss HP", value, &mut memory_values.boss_hp);
-        };
-    } else {
-        // with the current room id value as an offset, find its name in the array
-        let curr_room_name_add = process.read::<u64>(Address::new(
-            memory_addresses
-                .room_names
-                .unwrap_or(Address::new(0))
-                .value()
-                + memory_values.room_id.current as u64 * 0x8,
+    memory_values.read_fields(memory, memory_addresses, &buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_memory::MemoryImage;
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    const MODULE: u64 = 0x1_4000_0000;
+    const HEAP: u64 = 0x2000_0000;
+    // the room id instruction sits across the end of the first 0x1000 byte chunk of GameMemory::scan
+    const ROOM_ID_INSTRUCTION: u64 = 0xFFC;
+    const ROOM_ID: u64 = 0x2000;
+    const ROOM_NAMES_INSTRUCTION: u64 = 0x1800;
+    const ROOM_NAMES_POINTER: u64 = 0x2100;
+    const ROOMS_ARRAY: u64 = HEAP;
+    const ROOM_NAMES: [&str; 4] = ["Finalintro", "Mainmenu", "tower_entrancehall", "entrance_1"];
+    const BUFFER: u64 = HEAP + 0x1000;
+
+    /**
+     * runs the scans to the end, next_tick is pending once and ready when polled again
+     */
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    /// rip relative u32 from the end of an instruction in the module to a target in the module
+    fn displacement(instruction_end: u64, target: u64) -> [u8; 4] {
+        ((target - instruction_end) as u32).to_le_bytes()
+    }
+
+    fn buffer_bytes(room: &str) -> Vec<u8> {
+        let mut bytes = vec![0; MemoryValues::BUFFER_SIZE];
+        bytes[..16].copy_from_slice(&speedrun_buffer::MAGIC_PREFIX);
+        bytes[0x40..0x48].copy_from_slice(b"v1.1.063");
+        for (offset, value) in [(0x80, 12.0f64), (0x88, 34.5), (0x90, 1.0), (0x98, 2.5)] {
+            bytes[offset..offset + 8].copy_from_slice(&f64::to_le_bytes(value));
+        }
+        bytes[0xA0..0xA0 + room.len()].copy_from_slice(room.as_bytes());
+        bytes[0xE1] = 3;
+        bytes
+    }
+
+    /**
+     * the game with the room id, the room names array and the buffer where the signatures point, the current room is "entrance_1"
+     */
+    fn game() -> MemoryImage {
+        let mut module = vec![0; 0x3000];
+        let room_id = ROOM_ID_INSTRUCTION as usize;
+        module[room_id..room_id + 9].copy_from_slice(&[0x89, 0x3D, 0, 0, 0, 0, 0x48, 0x3B, 0x1D]);
+        module[room_id + 2..room_id + 6]
+            .copy_from_slice(&displacement(ROOM_ID_INSTRUCTION + 6, ROOM_ID));
+        module[ROOM_ID as usize..ROOM_ID as usize + 4].copy_from_slice(&3i32.to_le_bytes());
+
+        let room_names = ROOM_NAMES_INSTRUCTION as usize;
+        module[room_names..room_names + 13].copy_from_slice(&[
+            0x74, 0x0C, 0x48, 0x8B, 0x05, 0, 0, 0, 0, 0x48, 0x8B, 0x04, 0xD0,
+        ]);
+        module[room_names + 5..room_names + 9].copy_from_slice(&displacement(
+            ROOM_NAMES_INSTRUCTION + 9,
+            ROOM_NAMES_POINTER,
         ));
+        module[ROOM_NAMES_POINTER as usize..ROOM_NAMES_POINTER as usize + 8]
+            .copy_from_slice(&ROOMS_ARRAY.to_le_bytes());
+
+        let mut heap = vec![0; 0x2000];
+        for (index, name) in ROOM_NAMES.iter().enumerate() {
+            let name_offset = 0x100 + index * 0x20;
+            heap[index * 8..index * 8 + 8]
+                .copy_from_slice(&(HEAP + name_offset as u64).to_le_bytes());
+            heap[name_offset..name_offset + name.len()].copy_from_slice(name.as_bytes());
+        }
 
-        match curr_room_name_add {
-            Ok(add) => read_string_and_update_pair(
-                process,
-                Address::new(0),
-                &[add],
-                "Room Name (GM Array)",
-                &mut memory_values.room_name,
-            ),
-            Err(_) => return Err("Could not read the room address, retrying signature scan..."),
-        };
+        let mut game = MemoryImage::default();
+        game.add_region(Address::new(MODULE), module)
+            .add_region(Address::new(HEAP), heap);
+        game.write(Address::new(BUFFER), &buffer_bytes("entrance_1"))
+            .unwrap();
+        game
+    }
+
+    fn addresses() -> MemoryAddresses {
+        MemoryAddresses {
+            main_address: Some(Address::new(MODULE)),
+            main_module_size: Some(0x3000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn signatures_between_two_chunks_are_found() {
+        let signature = Signature::<9>::new("89 3D ?? ?? ?? ?? 48 3B 1D");
+
+        assert_eq!(
+            game().scan(&signature, (Address::new(MODULE), 0x3000)),
+            Some(Address::new(MODULE + ROOM_ID_INSTRUCTION))
+        );
+    }
+
+    #[test]
+    fn room_id_and_room_names_are_found_in_the_module() {
+        let game = game();
+
+        assert_eq!(
+            block_on(room_id_sigscan_start(&game, addresses())),
+            Ok(Address::new(ROOM_ID))
+        );
+        assert_eq!(
+            block_on(room_name_array_sigscan_start(&game, &addresses(), 3)),
+            Ok(Address::new(ROOMS_ARRAY))
+        );
+    }
+
+    #[test]
+    fn room_names_array_needs_an_anchor_room_and_the_room_id() {
+        let mut game = game();
+        // past the end of the array
+        assert!(block_on(room_name_array_sigscan_start(&game, &addresses(), 4)).is_err());
+
+        // "xinalintro" and "xower_entrancehall" are still room names, but not the ones every version has
+        for anchor in ["Finalintro", "tower_entrancehall"] {
+            let index = ROOM_NAMES.iter().position(|name| *name == anchor).unwrap();
+            game.write(Address::new(HEAP + 0x100 + index as u64 * 0x20), b"x")
+                .unwrap();
+        }
+        assert!(block_on(room_name_array_sigscan_start(&game, &addresses(), 3)).is_err());
+    }
+
+    #[test]
+    fn buffer_values_are_read_from_one_read_of_the_buffer() {
+        let game = game();
+        let mut addresses = addresses();
+        addresses.room_id = Some(Address::new(ROOM_ID));
+        addresses.buffer_helper = block_on(buffer_helper_sigscan_init(&game)).ok();
+        assert_eq!(addresses.buffer_helper, Some(Address::new(BUFFER)));
+
+        let mut values = MemoryValues::default();
+        refresh_mem_values(&game, &addresses, &mut values).unwrap();
+        assert!(values.buffer_valid);
+        assert_eq!(values.room_id.current, 3);
+        assert_eq!(values.game_version.current, "v1.1.063");
+        assert_eq!(values.file_minutes.current, 12.0);
+        assert_eq!(values.file_seconds.current, 34.5);
+        assert_eq!(values.level_seconds.current, 2.5);
+        assert_eq!(values.boss_hp.current, 3);
+        assert_eq!(values.room_name.current, "entrance_1");
     }
 
-    Ok(())
+    #[test]
+    fn a_broken_buffer_keeps_the_last_values() {
+        let mut game = game();
+        let mut addresses = addresses();
+        addresses.room_id = Some(Address::new(ROOM_ID));
+        addresses.buffer_helper = Some(Address::new(BUFFER));
+        let mut values = MemoryValues::default();
+        refresh_mem_values(&game, &addresses, &mut values).unwrap();
+
+        game.write(Address::new(BUFFER + 0x80), &f64::NAN.to_le_bytes())
+            .unwrap();
+        refresh_mem_values(&game, &addresses, &mut values).unwrap();
+        assert!(!values.buffer_valid);
+        assert_eq!(values.file_minutes.current, 12.0);
+        assert_eq!(values.room_name.current, "entrance_1");
+    }
+
+    #[test]
+    fn without_the_buffer_the_room_name_comes_from_the_array() {
+        let mut game = game();
+        game.write(Address::new(BUFFER), &[0; 16]).unwrap();
+        assert!(block_on(buffer_helper_sigscan_init(&game)).is_err());
+
+        let mut addresses = addresses();
+        addresses.room_id = Some(Address::new(ROOM_ID));
+        addresses.room_names = Some(Address::new(ROOMS_ARRAY));
+        let mut values = MemoryValues::default();
+        refresh_mem_values(&game, &addresses, &mut values).unwrap();
+        assert_eq!(values.room_name.current, "entrance_1");
+
+        game.write(Address::new(MODULE + ROOM_ID), &2i32.to_le_bytes())
+            .unwrap();
+        refresh_mem_values(&game, &addresses, &mut values).unwrap();
+        assert_eq!(values.room_name.current, "tower_entrancehall");
+        assert_eq!(values.room_name.old, "entrance_1");
+    }
+
+    /// steps the rescan until it finds something or a pass ends
+    fn finish_pass(rescan: &mut Rescan, game: &MemoryImage) -> Option<RescanStep> {
+        (0..RESCAN_INTERVAL_TICKS + 4)
+            .map(|_| rescan.step(game, &addresses(), 3))
+            .find(|step| *step != RescanStep::Pending)
+    }
+
+    #[test]
+    fn the_rescan_finds_a_buffer_allocated_later() {
+        let mut game = game();
+        game.write(Address::new(BUFFER), &[0; 16]).unwrap();
+        let mut rescan = Rescan::new(MemoryTarget::Buffer);
+        assert_eq!(finish_pass(&mut rescan, &game), Some(RescanStep::NotFound));
+
+        game.write(Address::new(BUFFER), &speedrun_buffer::MAGIC_PREFIX)
+            .unwrap();
+        rescan.start_now();
+        assert_eq!(
+            finish_pass(&mut rescan, &game),
+            Some(RescanStep::Found(Address::new(BUFFER)))
+        );
+    }
+
+    #[test]
+    fn the_rescan_follows_a_moved_room_names_array() {
+        let mut game = game();
+        let moved_array = HEAP + 0x800;
+        let names = game.read_array::<0x20>(Address::new(ROOMS_ARRAY)).unwrap();
+        game.write(Address::new(moved_array), &names).unwrap();
+        game.write(
+            Address::new(MODULE + ROOM_NAMES_POINTER),
+            &moved_array.to_le_bytes(),
+        )
+        .unwrap();
+
+        let mut rescan = Rescan::new(MemoryTarget::RoomNamesArray);
+        rescan.start_now();
+        assert_eq!(
+            finish_pass(&mut rescan, &game),
+            Some(RescanStep::Found(Address::new(moved_array)))
+        );
+    }
 }
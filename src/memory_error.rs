@@ -0,0 +1,113 @@
+use asr::Address;
+
+use crate::game_memory::CStringError;
+#[cfg(target_arch = "wasm32")]
+use crate::watchdog::StaleMemory;
+
+/**
+ * What the splitter was reading or looking for
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryTarget {
+    MainModule,
+    RoomId,
+    RoomNamesArray,
+    RoomName,
+    Buffer,
+}
+
+impl core::fmt::Display for MemoryTarget {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            MemoryTarget::MainModule => "main module",
+            MemoryTarget::RoomId => "room ID",
+            MemoryTarget::RoomNamesArray => "room names array",
+            MemoryTarget::RoomName => "room name",
+            MemoryTarget::Buffer => "helper buffer",
+        })
+    }
+}
+
+/**
+ * Why finding or reading the game memory failed
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryError {
+    /// a signature scan or the module lookup found nothing
+    NotFound { what: MemoryTarget },
+    /// the address is known but reading it failed
+    ReadFailed {
+        what: MemoryTarget,
+        address: Address,
+    },
+    /// a string had no end before its max length
+    Truncated {
+        what: MemoryTarget,
+        max_length: usize,
+    },
+    /// a string isn't UTF-8
+    InvalidUtf8 { what: MemoryTarget },
+}
+
+/**
+ * What the main loop does about an error, detaching is the only one that stops the current connection
+ */
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Recovery {
+    /// skip this tick and read again on the next one, a read can fail while the game writes to it
+    RetryRead,
+    /// find the address again without leaving the main loop
+    Rescan(StaleMemory),
+    /// start over from attaching to the game
+    Detach,
+}
+
+impl MemoryError {
+    /**
+     * errors while reading are recoverable, not finding something at all is fatal
+     */
+    #[cfg(target_arch = "wasm32")]
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            MemoryError::ReadFailed { .. } => Recovery::RetryRead,
+            // a room name that isn't a string means the array moved
+            MemoryError::Truncated {
+                what: MemoryTarget::RoomName,
+                ..
+            }
+            | MemoryError::InvalidUtf8 {
+                what: MemoryTarget::RoomName,
+            } => Recovery::Rescan(StaleMemory::RoomNames),
+            MemoryError::Truncated { .. }
+            | MemoryError::InvalidUtf8 { .. }
+            | MemoryError::NotFound { .. } => Recovery::Detach,
+        }
+    }
+
+    /**
+     * the error of reading the string of the target
+     */
+    pub fn from_c_string(err: CStringError, what: MemoryTarget) -> Self {
+        match err {
+            CStringError::ReadFailed { address } => MemoryError::ReadFailed { what, address },
+            CStringError::Truncated { max_length } => MemoryError::Truncated { what, max_length },
+            CStringError::InvalidUtf8 => MemoryError::InvalidUtf8 { what },
+        }
+    }
+}
+
+impl core::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MemoryError::NotFound { what } => write!(f, "Could not find the {what}"),
+            MemoryError::ReadFailed { what, address } => {
+                write!(f, "Could not read the {what} at {:X}", address.value())
+            }
+            MemoryError::Truncated { what, max_length } => {
+                write!(f, "The {what} is longer than {max_length} bytes")
+            }
+            MemoryError::InvalidUtf8 { what } => write!(f, "The {what} isn't valid UTF-8"),
+        }
+    }
+}
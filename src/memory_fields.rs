@@ -0,0 +1,477 @@
+use asr::watcher::Pair;
+
+// traces are only written by the runtime and the tests of the recording
+#[cfg(any(target_arch = "wasm32", test))]
+use core::fmt::Write;
+// the fields are only read in the runtime and in the tests of the memory code, the host replays them from traces
+#[cfg(any(target_arch = "wasm32", test))]
+use {
+    crate::{
+        game_memory::GameMemory,
+        memory_error::{MemoryError, MemoryTarget},
+        variables, MemoryAddresses,
+    },
+    asr::Address,
+};
+
+/// longest string read from memory, room names are far shorter so anything longer is garbage
+#[cfg(any(target_arch = "wasm32", test))]
+pub const MAX_STRING_LENGTH: usize = 0x200;
+
+/**
+ * A chain of pointers from an address the splitter found.
+ * Every offset but the last is added and dereferenced, the last one is added to get the address of the value
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub struct PointerPath {
+    pub what: MemoryTarget,
+    pub base: fn(&MemoryAddresses, &MemoryValues) -> Option<Address>,
+    pub offsets: &'static [u64],
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl PointerPath {
+    fn resolve<M: GameMemory>(
+        &self,
+        memory: &M,
+        addresses: &MemoryAddresses,
+        values: &MemoryValues,
+    ) -> Result<Address, MemoryError> {
+        let mut address =
+            (self.base)(addresses, values).ok_or(MemoryError::NotFound { what: self.what })?;
+        if let Some((last, dereferenced)) = self.offsets.split_last() {
+            for offset in dereferenced {
+                let pointer = Address::new(address.value() + offset);
+                address = Address::new(memory.read_u64(pointer).map_err(|_| {
+                    MemoryError::ReadFailed {
+                        what: self.what,
+                        address: pointer,
+                    }
+                })?);
+            }
+            address = Address::new(address.value() + last);
+        }
+        Ok(address)
+    }
+}
+
+/**
+ * Where a watched value is read from: its offset in the buffer when the buffer is found,
+ * and the pointer path when it isn't or the value isn't in the buffer
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub struct FieldSource {
+    pub buffer: Option<usize>,
+    pub pointer: Option<PointerPath>,
+}
+
+/// only in the buffer
+#[cfg(any(target_arch = "wasm32", test))]
+const fn buffer(offset: usize) -> FieldSource {
+    FieldSource {
+        buffer: Some(offset),
+        pointer: None,
+    }
+}
+
+/// always read from memory
+#[cfg(any(target_arch = "wasm32", test))]
+const fn pointer(path: PointerPath) -> FieldSource {
+    FieldSource {
+        buffer: None,
+        pointer: Some(path),
+    }
+}
+
+/// from the buffer if it's found, from memory otherwise
+#[cfg(any(target_arch = "wasm32", test))]
+const fn buffer_or_pointer(offset: usize, path: PointerPath) -> FieldSource {
+    FieldSource {
+        buffer: Some(offset),
+        pointer: Some(path),
+    }
+}
+
+/**
+ * What the buffer read of this tick gave
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub enum BufferRead<'a> {
+    /// there's no buffer, the fields that can be read from memory are
+    Missing,
+    /// the buffer is used but doesn't make sense this tick, its fields keep their last values
+    Invalid,
+    Valid(&'a [u8]),
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+enum FieldRead<'a> {
+    Skip,
+    Buffer(&'a [u8]),
+    Memory(Address, MemoryTarget),
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl FieldSource {
+    /**
+     * the bytes of a value of type T in the bytes of the whole buffer, None if it isn't in the buffer
+     */
+    pub fn buffer_bytes<'a, T: FieldValue>(&self, buffer: &'a [u8]) -> Option<&'a [u8]> {
+        let offset = self.buffer?;
+        buffer.get(offset..offset + T::BUFFER_SIZE)
+    }
+
+    fn locate<'a, T: FieldValue, M: GameMemory>(
+        &self,
+        memory: &M,
+        addresses: &MemoryAddresses,
+        values: &MemoryValues,
+        buffer: &BufferRead<'a>,
+    ) -> Result<FieldRead<'a>, MemoryError> {
+        match (buffer, self.buffer) {
+            (BufferRead::Valid(bytes), Some(_)) => {
+                return Ok(self
+                    .buffer_bytes::<T>(bytes)
+                    .map_or(FieldRead::Skip, FieldRead::Buffer))
+            }
+            (BufferRead::Invalid, Some(_)) => return Ok(FieldRead::Skip),
+            _ => {}
+        }
+        match &self.pointer {
+            Some(path) => Ok(FieldRead::Memory(
+                path.resolve(memory, addresses, values)?,
+                path.what,
+            )),
+            None => Ok(FieldRead::Skip),
+        }
+    }
+}
+
+/**
+ * returns true if the pair was updated
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+fn update<T: FieldValue, M: GameMemory>(
+    pair: &mut Pair<T>,
+    read: FieldRead,
+    memory: &M,
+) -> Result<bool, MemoryError> {
+    match read {
+        FieldRead::Skip => Ok(false),
+        // bytes that aren't a value of the type, like a room name that isn't UTF-8, keep the last value
+        FieldRead::Buffer(bytes) => Ok(T::update_from_bytes(pair, bytes).is_some()),
+        FieldRead::Memory(address, what) => {
+            T::update_from_memory(pair, memory, address, what).map(|_| true)
+        }
+    }
+}
+
+/**
+ * A type a watched value can have: how it's read from the buffer and from memory, and how it's written in traces and read back from them
+ */
+pub trait FieldValue: Sized {
+    /// bytes the value takes in the buffer
+    #[cfg(any(target_arch = "wasm32", test))]
+    const BUFFER_SIZE: usize;
+
+    /// None if the bytes aren't a value of the type
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_bytes(pair: &mut Pair<Self>, bytes: &[u8]) -> Option<()>;
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_memory<M: GameMemory>(
+        pair: &mut Pair<Self>,
+        memory: &M,
+        address: Address,
+        what: MemoryTarget,
+    ) -> Result<(), MemoryError>;
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn write_trace(&self, out: &mut String);
+
+    fn parse_trace(text: &str) -> Option<Self>;
+}
+
+pub fn set_pair<T>(pair: &mut Pair<T>, value: T) {
+    pair.old = core::mem::replace(&mut pair.current, value);
+}
+
+macro_rules! number_field_value {
+    ($($ty:ty),*) => {$(
+        impl FieldValue for $ty {
+            #[cfg(any(target_arch = "wasm32", test))]
+            const BUFFER_SIZE: usize = core::mem::size_of::<$ty>();
+
+            #[cfg(any(target_arch = "wasm32", test))]
+            fn update_from_bytes(pair: &mut Pair<Self>, bytes: &[u8]) -> Option<()> {
+                set_pair(pair, <$ty>::from_le_bytes(bytes.try_into().ok()?));
+                Some(())
+            }
+
+            #[cfg(any(target_arch = "wasm32", test))]
+            fn update_from_memory<M: GameMemory>(
+                pair: &mut Pair<Self>,
+                memory: &M,
+                address: Address,
+                what: MemoryTarget,
+            ) -> Result<(), MemoryError> {
+                let mut bytes = [0; core::mem::size_of::<$ty>()];
+                memory
+                    .read_bytes(address, &mut bytes)
+                    .map_err(|_| MemoryError::ReadFailed { what, address })?;
+                set_pair(pair, <$ty>::from_le_bytes(bytes));
+                Ok(())
+            }
+
+            #[cfg(any(target_arch = "wasm32", test))]
+            fn write_trace(&self, out: &mut String) {
+                let _ = write!(out, "{self}");
+            }
+
+            fn parse_trace(text: &str) -> Option<Self> {
+                text.parse().ok()
+            }
+        }
+    )*};
+}
+
+number_field_value!(i32, u8, f64);
+
+impl FieldValue for bool {
+    #[cfg(any(target_arch = "wasm32", test))]
+    const BUFFER_SIZE: usize = 1;
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_bytes(pair: &mut Pair<Self>, bytes: &[u8]) -> Option<()> {
+        set_pair(pair, *bytes.first()? != 0);
+        Some(())
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_memory<M: GameMemory>(
+        pair: &mut Pair<Self>,
+        memory: &M,
+        address: Address,
+        what: MemoryTarget,
+    ) -> Result<(), MemoryError> {
+        let byte = memory
+            .read_u8(address)
+            .map_err(|_| MemoryError::ReadFailed { what, address })?;
+        set_pair(pair, byte != 0);
+        Ok(())
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn write_trace(&self, out: &mut String) {
+        out.push(if *self { '1' } else { '0' });
+    }
+
+    fn parse_trace(text: &str) -> Option<Self> {
+        match text {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * UTF-8 string that ends with a 0 or at the end of the bytes
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn decode_string(bytes: &[u8]) -> Option<&str> {
+    let string_as_bytes = bytes.split(|byte| *byte == 0).next()?;
+    core::str::from_utf8(string_as_bytes).ok()
+}
+
+/**
+ * strings only allocate when they change
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+fn set_string_pair(pair: &mut Pair<String>, value: &str) {
+    if pair.current == value {
+        pair.old.clone_from(&pair.current);
+    } else {
+        pair.old = core::mem::replace(&mut pair.current, value.to_string());
+    }
+}
+
+impl FieldValue for String {
+    // every string of the buffer has this many bytes, the shorter ones end with a 0
+    #[cfg(any(target_arch = "wasm32", test))]
+    const BUFFER_SIZE: usize = 0x40;
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_bytes(pair: &mut Pair<Self>, bytes: &[u8]) -> Option<()> {
+        set_string_pair(pair, decode_string(bytes)?);
+        Some(())
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn update_from_memory<M: GameMemory>(
+        pair: &mut Pair<Self>,
+        memory: &M,
+        address: Address,
+        what: MemoryTarget,
+    ) -> Result<(), MemoryError> {
+        let mut buf = [0; MAX_STRING_LENGTH];
+        let value = memory
+            .read_c_string(address, &mut buf)
+            .map_err(|err| MemoryError::from_c_string(err, what))?;
+        set_string_pair(pair, value);
+        Ok(())
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn write_trace(&self, out: &mut String) {
+        out.push_str(self);
+    }
+
+    fn parse_trace(text: &str) -> Option<Self> {
+        Some(text.to_string())
+    }
+}
+
+/**
+ * Generates MemoryValues from the table of watched values, with the reads, the timer variables and the trace columns of every field.
+ * A field is `name: Type = source, visibility "Variable Name" "format";` where the source is buffer(offset), pointer(path)
+ * or buffer_or_pointer(offset, path), and visibility is user, debug or hidden
+ */
+macro_rules! memory_fields {
+    (@show user, $name:literal, $value:expr) => {
+        variables::set($name, $value)
+    };
+    (@show debug, $name:literal, $value:expr) => {
+        variables::set_debug($name, $value)
+    };
+    (@show hidden, $name:literal, $value:expr) => {};
+
+    ($(
+        $(#[doc = $doc:literal])*
+        $field:ident: $ty:ty = $source:expr, $visibility:ident $name:literal $format:literal;
+    )*) => {
+        #[derive(Default)]
+        pub struct MemoryValues {
+            $(
+                $(#[doc = $doc])*
+                pub $field: Pair<$ty>,
+            )*
+            /// false when the last buffer read didn't look like the buffer anymore
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub buffer_valid: bool,
+        }
+
+        /**
+         * where every field is read from, as the table says
+         */
+        #[cfg(any(target_arch = "wasm32", test))]
+        pub struct FieldSources {
+            $(pub $field: FieldSource,)*
+        }
+
+        impl MemoryValues {
+            /// the columns of a trace tick, in the order of the table
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub const FIELD_NAMES: [&'static str; [$(stringify!($field)),*].len()] = [$(stringify!($field)),*];
+
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub const SOURCES: FieldSources = FieldSources {
+                $($field: $source,)*
+            };
+
+            /// bytes read from the buffer every tick, up to the end of the last field in it
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub const BUFFER_SIZE: usize = {
+                let mut size = 0;
+                $(
+                    if let Some(offset) = Self::SOURCES.$field.buffer {
+                        let end = offset + <$ty as FieldValue>::BUFFER_SIZE;
+                        if end > size {
+                            size = end;
+                        }
+                    }
+                )*
+                size
+            };
+
+            /**
+             * updates every field that can be read this tick, in the order of the table so later ones can use earlier ones
+             */
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub fn read_fields<M: GameMemory>(
+                &mut self,
+                memory: &M,
+                addresses: &MemoryAddresses,
+                buffer: &BufferRead,
+            ) -> Result<(), MemoryError> {
+                $(
+                    let read = Self::SOURCES.$field.locate::<$ty, M>(memory, addresses, self, buffer)?;
+                    if update(&mut self.$field, read, memory)? {
+                        memory_fields!(@show $visibility, $name, format_args!($format, self.$field.current));
+                    }
+                )*
+                Ok(())
+            }
+
+            /**
+             * the current values as the tab separated columns of a trace tick
+             */
+            #[cfg(any(target_arch = "wasm32", test))]
+            pub fn write_trace(&self, out: &mut String) {
+                $(
+                    FieldValue::write_trace(&self.$field.current, out);
+                    out.push('\t');
+                )*
+                out.pop();
+            }
+
+            /**
+             * sets the field from its column in a trace tick, None if there's no such field or the value can't be parsed
+             */
+            pub fn set_from_trace(&mut self, field: &str, text: &str) -> Option<()> {
+                match field {
+                    $(stringify!($field) => set_pair(&mut self.$field, <$ty as FieldValue>::parse_trace(text)?),)*
+                    _ => return None,
+                }
+                Some(())
+            }
+        }
+    };
+}
+
+// the order is the order of the trace columns, the room name can have anything in it so it stays last.
+// the buffer offsets are the ones in speedrun_buffer.rs
+memory_fields! {
+    /// the id of the current room, the index of its name in the room names array
+    room_id: i32 = pointer(PointerPath {
+        what: MemoryTarget::RoomId,
+        base: |addresses, _| {
+            Some(Address::new(
+                addresses.main_address?.value() + addresses.room_id?.value(),
+            ))
+        },
+        offsets: &[],
+    }), debug "Room ID" "{}";
+    file_minutes: f64 = buffer(0x80), debug "File Minutes" "{}";
+    file_seconds: f64 = buffer(0x88), debug "File Seconds" "{}";
+    level_minutes: f64 = buffer(0x90), debug "Level Minutes" "{}";
+    level_seconds: f64 = buffer(0x98), debug "Level Seconds" "{}";
+    /// the fade at the end of a level exists
+    end_of_level: bool = buffer(0xE0), debug "End Fade Exists" "{}";
+    boss_hp: u8 = buffer(0xE1), debug "Boss HP" "{}";
+    /// the splitter status shows it
+    game_version: String = buffer(0x40), hidden "Game Version" "{}";
+    /// without the buffer the current room id is the index of the name in the room names array
+    room_name: String = buffer_or_pointer(
+        0xA0,
+        PointerPath {
+            what: MemoryTarget::RoomName,
+            base: |addresses, values| {
+                Some(Address::new(
+                    addresses.room_names?.value() + values.room_id.current as u64 * 0x8,
+                ))
+            },
+            offsets: &[0, 0],
+        },
+    ), user "Room Name" "{}";
+}
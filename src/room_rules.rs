@@ -0,0 +1,121 @@
+/**
+ * A user defined split on going from one room to another, like "war_13 -> tower_5". Both sides can use * as a wildcard
+ */
+#[derive(PartialEq, Debug)]
+pub struct RoomTransitionRule {
+    old_room: String,
+    new_room: String,
+}
+
+/**
+ * true if the text matches the pattern, where * matches any amount of characters
+ */
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut pattern_index, mut text_index) = (0, 0);
+    // where the last * was and the text position it is matching up to, to backtrack
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while text_index < text.len() {
+        if pattern.get(pattern_index) == Some(&b'*') {
+            last_star = Some((pattern_index, text_index));
+            pattern_index += 1;
+        } else if pattern.get(pattern_index) == Some(&text[text_index]) {
+            pattern_index += 1;
+            text_index += 1;
+        } else if let Some((star_index, star_text_index)) = last_star {
+            // make the last * eat one more character and try again
+            pattern_index = star_index + 1;
+            text_index = star_text_index + 1;
+            last_star = Some((star_index, text_index));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|byte| *byte == b'*')
+}
+
+impl RoomTransitionRule {
+    pub fn matches(&self, old_room: &str, new_room: &str) -> bool {
+        wildcard_match(&self.old_room, old_room) && wildcard_match(&self.new_room, new_room)
+    }
+}
+
+/**
+ * Parses rules separated by commas, semicolons or new lines, like "forest_john -> forest_escape1, war_13 -> tower_*"
+ */
+pub fn parse_rules(text: &str) -> Result<Vec<RoomTransitionRule>, String> {
+    text.split([',', ';', '\n'])
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| match rule.split_once("->") {
+            Some((old_room, new_room))
+                if !old_room.trim().is_empty() && !new_room.trim().is_empty() =>
+            {
+                Ok(RoomTransitionRule {
+                    old_room: old_room.trim().to_string(),
+                    new_room: new_room.trim().to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid room transition rule \"{rule}\", expected \"old_room -> new_room\""
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(old_room: &str, new_room: &str) -> RoomTransitionRule {
+        RoomTransitionRule {
+            old_room: old_room.to_string(),
+            new_room: new_room.to_string(),
+        }
+    }
+
+    #[test]
+    fn wildcards_backtrack() {
+        assert!(wildcard_match("a*b*c", "abc"));
+        assert!(wildcard_match("a*b*c", "axxbyybzc"));
+        // the first b isn't the one to stop at
+        assert!(wildcard_match("a*bc", "abxbc"));
+        assert!(!wildcard_match("a*b*c", "axxbyy"));
+        assert!(!wildcard_match("a*b*c", "abcx"));
+    }
+
+    #[test]
+    fn a_bare_star_matches_anything() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "tower_5"));
+        assert!(wildcard_match("tower_*", "tower_"));
+        assert!(!wildcard_match("tower_", "tower_5"));
+        assert!(!wildcard_match("", "tower_5"));
+    }
+
+    #[test]
+    fn rules_are_split_on_every_separator() {
+        assert_eq!(
+            parse_rules("war_13 -> tower_*, a->b;c -> d\n * -> tower_5 ,,"),
+            Ok(vec![
+                rule("war_13", "tower_*"),
+                rule("a", "b"),
+                rule("c", "d"),
+                rule("*", "tower_5"),
+            ])
+        );
+        assert_eq!(parse_rules(" \n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn rules_need_both_sides() {
+        assert!(parse_rules("war_13 ->").is_err());
+        assert!(parse_rules("-> tower_5").is_err());
+        assert!(parse_rules(" -> ").is_err());
+        assert!(parse_rules("war_13 tower_5").is_err());
+        // one bad rule and none are loaded
+        assert!(parse_rules("a -> b, c").is_err());
+    }
+}
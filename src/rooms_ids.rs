@@ -1,4 +1,4 @@
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
 pub enum Level {
     Hub,
     F1Tutorial,
@@ -31,166 +31,368 @@ pub enum Level {
     Fake,
     PizzaFace,
     ResultsScreen,
+    #[default]
     Unknown,
 }
 
+/**
+ * Where a level is in the tower, also used to group them in the settings
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Floor {
+    /// not a level, the rooms between them
+    Hub,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    /// secrets and bonus levels
+    Extra,
+}
+
+impl Floor {
+    /**
+     * the title of the floor above its level end split checkboxes, the levels of floors without one always split
+     */
+    pub fn splits_title(self) -> Option<&'static str> {
+        match self {
+            Floor::F1 => Some("Floor 1 Splits"),
+            Floor::F2 => Some("Floor 2 Splits"),
+            Floor::F3 => Some("Floor 3 Splits"),
+            Floor::F4 => Some("Floor 4 Splits"),
+            Floor::F5 => Some("Floor 5 Splits"),
+            Floor::Hub | Floor::Extra => None,
+        }
+    }
+}
+
+/**
+ * Everything the splitter knows about a level
+ */
+pub struct LevelInfo {
+    pub level: Level,
+    pub name: &'static str,
+    pub floor: Floor,
+    /// any room with a name starting with one of these is part of the level, the longest match wins
+    pub room_prefixes: &'static [&'static str],
+    /// the first room of the level, where IL runs start
+    pub start_room: &'static str,
+    /// rooms that enable the full game split of the level, usually the escape starts there
+    pub unlock_rooms: &'static [&'static str],
+    /// rooms that split on exiting them, usually where the levels end
+    pub exit_rooms: &'static [&'static str],
+    /// only ends once the boss hp is gone, leaving the room isn't enough
+    pub boss: bool,
+}
+
+const fn level(
+    level: Level,
+    name: &'static str,
+    floor: Floor,
+    room_prefixes: &'static [&'static str],
+    start_room: &'static str,
+    unlock_rooms: &'static [&'static str],
+    exit_rooms: &'static [&'static str],
+) -> LevelInfo {
+    LevelInfo {
+        level,
+        name,
+        floor,
+        room_prefixes,
+        start_room,
+        unlock_rooms,
+        exit_rooms,
+        boss: false,
+    }
+}
+
+const fn boss(info: LevelInfo) -> LevelInfo {
+    LevelInfo { boss: true, ..info }
+}
+
+#[rustfmt::skip]
+pub const LEVELS: &[LevelInfo] = &[
+    level(Level::Hub, "Hub", Floor::Hub, &["tower_", "boss_pizzafacehub"], "", &[], &[]),
+    level(Level::ResultsScreen, "Results Screen", Floor::Hub, &["rank_room"], "", &[], &["rank_room"]),
+
+    level(Level::F1Tutorial, "Tutorial", Floor::F1, &["tower_tutorial"], "tower_tutorial1", &["tower_tutorial10"], &["tower_tutorial1"]),
+    level(Level::F1TutorialNoise, "Tutorial (Noise)", Floor::F1, &["tower_tutorial1N", "tower_tutorial2N", "tower_tutorial3N"], "tower_tutorial1N", &["tower_tutorial3N"], &["tower_tutorial1N"]),
+    level(Level::F1JohnGutter, "John Gutter", Floor::F1, &["entrance_"], "entrance_1", &["entrance_10"], &["entrance_1"]),
+    level(Level::F1Pizzascape, "Pizzascape", Floor::F1, &["medieval_"], "medieval_1", &["medieval_10"], &["medieval_1"]),
+    level(Level::F1AncientCheese, "Ancient Cheese", Floor::F1, &["ruin_"], "ruin_1", &["ruin_11"], &["ruin_1"]),
+    level(Level::F1BloodsauceDungeon, "Bloodsauce Dungeon", Floor::F1, &["dungeon_"], "dungeon_1", &["dungeon_10"], &["dungeon_1"]),
+    boss(level(Level::Pepperman, "Pepperman", Floor::F1, &["boss_pepperman"], "boss_pepperman", &["boss_pepperman"], &["boss_pepperman"])),
+
+    level(Level::F2OreganoDesert, "Oregano Desert", Floor::F2, &["badland_"], "badland_1", &["badland_9"], &["badland_1"]),
+    level(Level::F2Wasteyard, "Wasteyard", Floor::F2, &["graveyard_"], "graveyard_1", &["graveyard_6"], &["graveyard_1"]),
+    level(Level::F2FunFarm, "Fun Farm", Floor::F2, &["farm_"], "farm_2", &["farm_11"], &["farm_2"]),
+    level(Level::F2FastfoodSaloon, "Fastfood Saloon", Floor::F2, &["saloon_"], "saloon_1", &["saloon_6"], &["saloon_1"]),
+    boss(level(Level::Vigilante, "The Vigilante", Floor::F2, &["boss_vigilante"], "boss_vigilante", &["boss_vigilante"], &["boss_vigilante"])),
+
+    level(Level::F3CrustCove, "Crust Cove", Floor::F3, &["plage_"], "plage_entrance", &["plage_cavern2"], &["plage_entrance"]),
+    level(Level::F3GnomeForest, "Gnome Forest", Floor::F3, &["forest_"], "forest_1", &["forest_john"], &["forest_1"]),
+    level(Level::F3Golf, "GOLF", Floor::F3, &["minigolf_"], "minigolf_1", &["minigolf_8"], &["minigolf_1"]),
+    level(Level::F3DeepDish9, "Deep-Dish 9", Floor::F3, &["space_"], "space_1", &["space_9"], &["space_1"]),
+    boss(level(Level::Noise, "The Noise", Floor::F3, &["boss_noise"], "boss_noise", &["boss_noise"], &["boss_noise"])),
+
+    level(Level::F4ThePigCity, "The Pig City", Floor::F4, &["street_"], "street_intro", &["street_john"], &["street_intro"]),
+    level(Level::F4OhShit, "Oh Shit!", Floor::F4, &["sewer_"], "sewer_1", &["sewer_8"], &["sewer_1"]),
+    level(Level::F4PeppibotFactory, "Peppibot Factory", Floor::F4, &["industrial_"], "industrial_1", &["industrial_5"], &["industrial_1"]),
+    level(Level::F4Refrigerator, "Refrigerator-Refrigerador-Freezerator", Floor::F4, &["freezer_"], "freezer_1", &["freezer_escape1"], &["freezer_1"]),
+    boss(level(Level::Fake, "Fake Peppino", Floor::F4, &["boss_fakepep"], "boss_fakepep", &["boss_fakepepkey"], &["boss_fakepepkey"])),
+
+    level(Level::F5Pizzascare, "Pizzascare", Floor::F5, &["chateau_"], "chateau_1", &["chateau_9"], &["chateau_1"]),
+    level(Level::F5DMAS, "Don't Make A Sound", Floor::F5, &["kidsparty_"], "kidsparty_1", &["kidsparty_john"], &["kidsparty_1"]),
+    level(Level::F5War, "WAR", Floor::F5, &["war_"], "war_1", &["war_1"], &["war_13"]),
+    boss(level(Level::PizzaFace, "Pizzaface", Floor::F5, &["boss_pizzaface"], "boss_pizzaface", &["boss_pizzaface"], &["boss_pizzaface", "boss_pizzafacefinale"])),
+    // ends with the end of level fade, not by exiting a room
+    level(Level::F5CrumblingTower, "The Crumbling Tower of Pizza", Floor::F5, &["tower_finalhallway"], "tower_finalhallway", &[], &[]),
+
+    level(Level::SecretsOfTheWorld, "Secrets of the World", Floor::Extra, &["secret_entrance"], "secret_entrance", &[], &[]),
+    level(Level::TrickyTreat, "Tricky Treat", Floor::Extra, &["trickytreat_"], "trickytreat_2", &[], &[]),
+];
+
+/// rooms that are known but aren't part of any level, like menus and loading screens.
+/// Rooms that aren't here or in LEVELS show up in the "Unknown Room" variable, add them to one of the two
+const NON_LEVEL_ROOMS: &[&str] = &[
+    "Finalintro",
+    "Longintro",
+    "Mainmenu",
+    "hub_loadingscreen",
+    "Endingroom",
+    "Creditsroom",
+    "Johnresurrectionroom",
+];
+
+// checked when compiling: every level is in the table once and has what the splits need
+const _: () = {
+    let mut found = [false; Level::Unknown as usize];
+    let mut i = 0;
+    while i < LEVELS.len() {
+        let info = &LEVELS[i];
+        let index = info.level as usize;
+        assert!(index < found.len(), "Level::Unknown can't be in LEVELS");
+        assert!(!found[index], "a level is repeated in LEVELS");
+        found[index] = true;
+
+        assert!(!info.name.is_empty(), "a level has no name");
+        assert!(!info.room_prefixes.is_empty(), "a level has no rooms");
+        assert!(
+            rooms_in_level(info.unlock_rooms, info.level)
+                && rooms_in_level(info.exit_rooms, info.level),
+            "an unlock or exit room is part of another level"
+        );
+        if !matches!(info.floor, Floor::Hub) {
+            assert!(!info.start_room.is_empty(), "a level has no start room");
+            assert!(
+                rooms_in_level(&[info.start_room], info.level),
+                "a start room is part of another level"
+            );
+            assert!(
+                info.unlock_rooms.is_empty() == info.exit_rooms.is_empty(),
+                "a level can be unlocked but never split or the other way around"
+            );
+        }
+        i += 1;
+    }
+
+    let mut level = 0;
+    while level < found.len() {
+        assert!(found[level], "a level is missing from LEVELS");
+        level += 1;
+    }
+};
+
+pub fn get_level_info(level: &Level) -> Option<&'static LevelInfo> {
+    LEVELS.iter().find(|info| info.level == *level)
+}
+
+const fn starts_with(name: &str, prefix: &str) -> bool {
+    let (name, prefix) = (name.as_bytes(), prefix.as_bytes());
+    if prefix.len() > name.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if name[i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/**
+ * The level a room belongs to by its exact prefix, None if it isn't part of any level.
+ * Const so the table can be checked with it when compiling
+ */
+const fn classify_room(room_name: &str) -> Option<Level> {
+    // the longest prefix is the most specific, like "tower_tutorial" over "tower_"
+    let mut found = None;
+    let mut longest = 0;
+    let mut i = 0;
+    while i < LEVELS.len() {
+        let prefixes = LEVELS[i].room_prefixes;
+        let mut j = 0;
+        while j < prefixes.len() {
+            if prefixes[j].len() > longest && starts_with(room_name, prefixes[j]) {
+                found = Some(LEVELS[i].level);
+                longest = prefixes[j].len();
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    found
+}
+
+/// true if every room is part of the level, they would start or split another level otherwise
+const fn rooms_in_level(rooms: &[&str], level: Level) -> bool {
+    let mut i = 0;
+    while i < rooms.len() {
+        match classify_room(rooms[i]) {
+            Some(found) if found as usize == level as usize => {}
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+/**
+ * Returns false for rooms that aren't in any level or in the known menus, the tables need updating if that happens
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn is_known_room(room_name: &str) -> bool {
+    classify_room(room_name).is_some() || NON_LEVEL_ROOMS.contains(&room_name)
+}
+
+/// rooms every version of the game has, the room names array has to have one of them
+#[cfg(any(target_arch = "wasm32", test))]
+pub const ANCHOR_ROOMS: [&str; 2] = ["tower_entrancehall", "Finalintro"];
+
+/**
+ * Could be the name of a room, known or not: room names are identifiers, so anything else is garbage read from the wrong place
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn is_valid_room_name(room_name: &str) -> bool {
+    !room_name.is_empty()
+        && room_name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/**
+ * Menus, intros and loading screens, the time spent in them isn't gameplay
+ */
+pub fn is_loading_room(room_name: &str) -> bool {
+    NON_LEVEL_ROOMS.contains(&room_name)
+}
+
+/**
+ * Rooms of the hub and the levels, the in game timer runs in all of them. The results screen is a level of its own but stops it
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn is_gameplay_room(room_name: &str) -> bool {
+    classify_room(room_name).is_some_and(|level| level != Level::ResultsScreen)
+}
+
+/**
+ * The secret rooms of a level are named with one of its prefixes then "secret", like "entrance_secret1"
+ */
+fn is_secret_room(room_name: &str) -> bool {
+    LEVELS
+        .iter()
+        .flat_map(|info| info.room_prefixes)
+        .any(|prefix| {
+            room_name
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with("secret"))
+        })
+}
+
 pub fn get_current_level(room_name: &str, prev_level: Level) -> Level {
     // special cases for rooms that overlap in multiple levels
     if prev_level == Level::F5CrumblingTower
-        && room_name.contains("tower_")
+        && room_name.starts_with("tower_")
         && room_name != "tower_pizzafacehall"
     {
         return Level::F5CrumblingTower;
     }
 
-    if prev_level == Level::SecretsOfTheWorld && room_name.contains("secret") {
+    if prev_level == Level::SecretsOfTheWorld && is_secret_room(room_name) {
         return Level::SecretsOfTheWorld;
     }
 
-    match room_name {
-        "tower_finalhallway" => Level::F5CrumblingTower,
-        x if x.contains("tower_tutorial1N")
-            || x.contains("tower_tutorial2N")
-            || x.contains("tower_tutorial3N") =>
-        {
-            Level::F1TutorialNoise
-        }
-        x if x.contains("tower_tutorial") => Level::F1Tutorial,
-        x if x.contains("tower_") || x == "boss_pizzafacehub" => Level::Hub,
-        x if x.contains("entrance_") => Level::F1JohnGutter,
-        x if x.contains("medieval_") => Level::F1Pizzascape,
-        x if x.contains("ruin_") => Level::F1AncientCheese,
-        x if x.contains("dungeon_") => Level::F1BloodsauceDungeon,
-        "boss_pepperman" => Level::Pepperman,
-        x if x.contains("badland_") => Level::F2OreganoDesert,
-        x if x.contains("graveyard_") => Level::F2Wasteyard,
-        x if x.contains("farm_") => Level::F2FunFarm,
-        x if x.contains("saloon_") => Level::F2FastfoodSaloon,
-        "boss_vigilante" => Level::Vigilante,
-        x if x.contains("plage_") => Level::F3CrustCove,
-        x if x.contains("forest_") => Level::F3GnomeForest,
-        x if x.contains("space_") => Level::F3DeepDish9,
-        x if x.contains("minigolf_") => Level::F3Golf,
-        "boss_noise" => Level::Noise,
-        x if x.contains("street_") => Level::F4ThePigCity,
-        x if x.contains("industrial_") => Level::F4PeppibotFactory,
-        x if x.contains("sewer_") => Level::F4OhShit,
-        x if x.contains("freezer_") => Level::F4Refrigerator,
-        x if x.contains("boss_fakepep") => Level::Fake,
-        x if x.contains("secret_entrance") => Level::SecretsOfTheWorld,
-        x if x.contains("trickytreat") => Level::TrickyTreat,
-        x if x.contains("chateau_") => Level::F5Pizzascare,
-        x if x.contains("kidsparty_") => Level::F5DMAS,
-        x if x.contains("war_") => Level::F5War,
-        "boss_pizzaface" => Level::PizzaFace,
-        "rank_room" => Level::ResultsScreen,
-        _ => Level::Unknown, // where did you go?
-    }
+    classify_room(room_name).unwrap_or(Level::Unknown) // where did you go?
 }
 
-pub fn get_starting_room<'a>(level: &Level) -> &'a str {
-    match level {
-        Level::F1Tutorial => "tower_tutorial1",
-        Level::F1TutorialNoise => "tower_tutorial1N",
-        Level::F1JohnGutter => "entrance_1",
-        Level::F1Pizzascape => "medieval_1",
-        Level::F1AncientCheese => "ruin_1",
-        Level::F1BloodsauceDungeon => "dungeon_1",
-        Level::F2OreganoDesert => "badland_1",
-        Level::F2Wasteyard => "graveyard_1",
-        Level::F2FunFarm => "farm_2",
-        Level::F2FastfoodSaloon => "saloon_1",
-        Level::F3CrustCove => "plage_entrance",
-        Level::F3GnomeForest => "forest_1",
-        Level::F3Golf => "minigolf_1",
-        Level::F3DeepDish9 => "space_1",
-        Level::F4ThePigCity => "street_intro",
-        Level::F4OhShit => "sewer_1",
-        Level::F4PeppibotFactory => "industrial_1",
-        Level::F4Refrigerator => "freezer_1",
-        Level::F5Pizzascare => "chateau_1",
-        Level::F5DMAS => "kidsparty_1",
-        Level::F5War => "war_1",
-        Level::F5CrumblingTower => "tower_finalhallway",
-        Level::SecretsOfTheWorld => "secret_entrance",
-        Level::TrickyTreat => "trickytreat_2",
-        Level::Pepperman => "boss_pepperman",
-        Level::Vigilante => "boss_vigilante",
-        Level::Noise => "boss_noise",
-        Level::Fake => "boss_fakepep",
-        Level::PizzaFace => "boss_pizzaface",
+pub fn get_starting_room(level: &Level) -> &'static str {
+    match get_level_info(level) {
+        Some(info) if !info.start_room.is_empty() => info.start_room,
         _ => "-",
     }
 }
 
 /**
- * Returns true if a key room that should enable the split for the current level (in full game) is received
+ * The level whose full game split a key room enables, usually where the escape starts
  */
-pub fn full_game_split_unlock_rooms(current_room: &str) -> bool {
-    [
-        "tower_tutorial10",
-        "tower_tutorial3N",
-        "entrance_10",
-        "medieval_10",
-        "ruin_11",
-        "dungeon_10",
-        "badland_9",
-        "graveyard_6",
-        "farm_11",
-        "saloon_6",
-        "plage_cavern2",
-        "forest_john",
-        "space_9",
-        "minigolf_8",
-        "street_john",
-        "sewer_8",
-        "industrial_5",
-        "freezer_escape1",
-        "chateau_9",
-        "kidsparty_john",
-        "war_1",
-        "boss_pepperman",
-        "boss_vigilante",
-        "boss_noise",
-        "boss_fakepepkey",
-        "boss_pizzaface",
-    ]
-    .contains(&current_room)
+pub fn get_unlocked_level(current_room: &str) -> Option<Level> {
+    LEVELS
+        .iter()
+        .find(|info| info.unlock_rooms.contains(&current_room))
+        .map(|info| info.level)
 }
 
 /**
  * Return true if it receives a room that should trigger a split, usually where the levels end
  */
 pub fn full_game_split_rooms(exited_level: &str) -> bool {
-    [
-        "tower_tutorial1",
-        "tower_tutorial1N",
-        "entrance_1",
-        "medieval_1",
-        "ruin_1",
-        "dungeon_1",
-        "badland_1",
-        "graveyard_1",
-        "farm_2",
-        "saloon_1",
-        "plage_entrance",
-        "forest_1",
-        "minigolf_1",
-        "space_1",
-        "street_intro",
-        "sewer_1",
-        "industrial_1",
-        "freezer_1",
-        "chateau_1",
-        "kidsparty_1",
-        "war_13",
-        "boss_pepperman",
-        "boss_vigilante",
-        "boss_noise",
-        "boss_fakepepkey",
-        "boss_pizzaface",
-        "boss_pizzafacefinale",
-        "rank_room",
-    ]
-    .contains(&exited_level)
+    LEVELS
+        .iter()
+        .any(|info| info.exit_rooms.contains(&exited_level))
+}
+
+/**
+ * The level that ends by exiting this room, if any
+ */
+pub fn get_exited_level(exited_room: &str) -> Option<&'static LevelInfo> {
+    LEVELS
+        .iter()
+        .find(|info| info.exit_rooms.contains(&exited_room))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_of_the_world_keeps_the_secret_rooms_of_levels() {
+        let secrets = Level::SecretsOfTheWorld;
+        assert_eq!(get_current_level("entrance_secret1", secrets), secrets);
+        assert_eq!(get_current_level("tower_secret2", secrets), secrets);
+
+        assert_eq!(
+            get_current_level("entrance_1", secrets),
+            Level::F1JohnGutter
+        );
+        // "secret" has to follow a level prefix
+        assert_eq!(
+            get_current_level("Mainmenu_secret", secrets),
+            Level::Unknown
+        );
+        assert_eq!(
+            get_current_level("entrance_secret1", Level::F1JohnGutter),
+            Level::F1JohnGutter
+        );
+    }
+
+    #[test]
+    fn the_results_screen_and_menus_arent_gameplay() {
+        assert!(is_gameplay_room("tower_entrancehall"));
+        assert!(is_gameplay_room("entrance_1"));
+        assert!(!is_gameplay_room("rank_room"));
+        assert!(!is_gameplay_room("Mainmenu"));
+    }
 }
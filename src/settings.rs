@@ -1,8 +1,18 @@
-use asr::print_message;
-use asr::settings::gui::Title;
-use asr::settings::Gui;
+#[cfg(target_arch = "wasm32")]
+use crate::logging;
+use crate::rooms_ids::{Level, LevelInfo, LEVELS};
+use asr::settings::gui::{self, Title, Widget};
+use asr::settings::{Gui, Map};
 use asr::watcher::Pair;
 
+/// key of the custom room transition rules in the settings map. The settings GUI has no text box and the runtime can't read
+/// files, so runners can't set them yet and they aren't documented for them. Only traces and layouts edited by hand have them
+#[cfg(target_arch = "wasm32")]
+pub const TRANSITION_RULES_KEY: &str = "splits_transition_rules";
+/// longest rules text that is read, so it can be compared with the last one every tick without allocating
+#[cfg(target_arch = "wasm32")]
+pub const TRANSITION_RULES_MAX_LENGTH: usize = 1024;
+
 #[derive(Gui, Clone, Copy, PartialEq, std::cmp::Eq, Debug)]
 pub enum TimerMode {
     /// Full Game
@@ -16,6 +26,118 @@ pub enum TimerMode {
     IW,
 }
 
+/**
+ * How much goes to the log, picked in the settings
+ */
+#[derive(Gui, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LogLevel {
+    /// Errors
+    Error,
+    /// Warnings
+    Warn,
+    /// Info
+    #[default]
+    Info,
+    /// Debug
+    Debug,
+    /// Everything
+    Trace,
+}
+
+/**
+ * The "On ending a level" checkbox of every level in the floors, registered from LEVELS with a title for each floor
+ */
+pub struct LevelSplits {
+    /// the level, the settings key of its checkbox and if it's picked, in the order of LEVELS
+    levels: Vec<(&'static LevelInfo, String, bool)>,
+}
+
+#[derive(Default)]
+pub struct LevelSplitsArgs {}
+
+impl LevelSplits {
+    fn is_enabled(&self, level: &Level) -> bool {
+        !self
+            .levels
+            .iter()
+            .any(|(info, _, enabled)| info.level == *level && !enabled)
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    fn flags(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.levels
+            .iter()
+            .map(|(_, key, enabled)| (key.as_str(), *enabled))
+    }
+
+    fn set(&mut self, key: &str, value: bool) -> bool {
+        match self
+            .levels
+            .iter_mut()
+            .find(|(_, level_key, _)| level_key == key)
+        {
+            Some((_, _, enabled)) => {
+                *enabled = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for LevelSplits {
+    fn default() -> Self {
+        Self {
+            levels: LEVELS
+                .iter()
+                .filter(|info| info.floor.splits_title().is_some())
+                .map(|info| (info, format!("split_{:?}", info.level), true))
+                .collect(),
+        }
+    }
+}
+
+impl Widget for LevelSplits {
+    type Args = LevelSplitsArgs;
+
+    fn register(key: &str, description: &str, _: Self::Args) -> Self {
+        gui::add_title(key, description, 1);
+
+        let mut splits = Self::default();
+        let mut floor = None;
+        for (info, level_key, enabled) in &mut splits.levels {
+            if floor != Some(info.floor) {
+                floor = Some(info.floor);
+                if let Some(title) = info.floor.splits_title() {
+                    gui::add_title(&format!("splits_floor_{:?}", info.floor), title, 2);
+                }
+            }
+            *enabled = gui::add_bool(level_key, info.name, true);
+            match info.level {
+                Level::PizzaFace => {
+                    gui::set_tooltip(level_key, "Also the split for entering CTOP out of bounds")
+                }
+                Level::F5CrumblingTower => gui::set_tooltip(level_key, "The end of the run"),
+                _ => {}
+            }
+        }
+        splits
+    }
+
+    fn update_from(&mut self, settings_map: &Map, _: &str, _: Self::Args) {
+        for (_, level_key, enabled) in &mut self.levels {
+            if let Some(value) = settings_map
+                .get(level_key)
+                .and_then(|value| value.get_bool())
+            {
+                *enabled = value;
+            }
+        }
+    }
+}
+
+// the debug settings are only read by the runtime, the host only replays the split settings
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
 #[derive(Gui)]
 pub struct Settings {
     /// NOTE: Use "-livesplit" as a launch options to use game time.
@@ -31,6 +153,12 @@ pub struct Settings {
     /// Load recommended settings when switching mode
     pub timer_mode_load_defaults: bool,
 
+    #[default = true]
+    /// Remove loads when "-livesplit" isn't used
+    ///
+    /// Without the launch option there's no in game time, so game time becomes real time paused in menus and loading screens
+    pub fallback_load_removal: bool,
+
     /// Start Options
     _timer_mode_title: Title,
 
@@ -73,6 +201,11 @@ pub struct Settings {
     /// On room change
     pub splits_rooms: bool,
 
+    /// Level End Splits
+    ///
+    /// Pick which levels split with "On ending a level", in every timer mode
+    pub level_splits: LevelSplits,
+
     /// Reset Options
     _reset_title: Title,
 
@@ -93,11 +226,139 @@ pub struct Settings {
     #[default = true]
     /// On restarting a level
     pub reset_new_level: bool,
+
+    /// Debug
+    _debug_title: Title,
+
+    #[default = false]
+    /// Record a trace of the memory values
+    ///
+    /// Prints every tick to the log so a missed or double split can be replayed, attach it to bug reports. Recording starts when the splitter attaches to the game
+    pub debug_record_trace: bool,
+
+    #[default = true]
+    /// Show debug variables
+    ///
+    /// Raw values like the memory addresses and the in game time minutes and seconds, the level, room and formatted times are always shown
+    pub debug_show_variables: bool,
+
+    /// Log verbosity
+    ///
+    /// How much the splitter prints to the log, the debug messages are always kept for the log dump
+    pub log_level: Pair<LogLevel>,
+
+    /// Dump the recent log
+    ///
+    /// Flip this to print the last lines of the log again, paste them into bug reports
+    pub debug_dump_log: Pair<bool>,
+}
+
+/**
+ * the GUI is never registered outside of the runtime, this is what replaying traces starts from. Same as the #[default]s above
+ */
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            _message: Title,
+            _igt_mode: Title,
+            timer_mode: Pair {
+                old: TimerMode::FullGame,
+                current: TimerMode::FullGame,
+            },
+            timer_mode_load_defaults: true,
+            fallback_load_removal: true,
+            _timer_mode_title: Title,
+            start_enable: true,
+            start_new_file: true,
+            start_any_file: false,
+            start_new_il: false,
+            start_exit_level: false,
+            _splits_title: Title,
+            splits_enable: true,
+            splits_level_end: true,
+            splits_rooms: false,
+            level_splits: LevelSplits::default(),
+            _reset_title: Title,
+            reset_enable: true,
+            reset_new_file: true,
+            reset_any_file: true,
+            reset_new_level: true,
+            _debug_title: Title,
+            debug_record_trace: false,
+            debug_show_variables: true,
+            log_level: Pair {
+                old: LogLevel::Info,
+                current: LogLevel::Info,
+            },
+            debug_dump_log: Pair {
+                old: false,
+                current: false,
+            },
+        }
+    }
+}
+
+impl Settings {
+    /**
+     * every checkbox that changes how the splits work, used to save and restore them in traces
+     */
+    #[cfg(any(target_arch = "wasm32", test))]
+    pub fn flags(&self) -> Vec<(&str, bool)> {
+        let mut flags = vec![
+            ("fallback_load_removal", self.fallback_load_removal),
+            ("start_enable", self.start_enable),
+            ("start_new_file", self.start_new_file),
+            ("start_any_file", self.start_any_file),
+            ("start_new_il", self.start_new_il),
+            ("start_exit_level", self.start_exit_level),
+            ("splits_enable", self.splits_enable),
+            ("splits_level_end", self.splits_level_end),
+            ("splits_rooms", self.splits_rooms),
+            ("reset_enable", self.reset_enable),
+            ("reset_new_file", self.reset_new_file),
+            ("reset_any_file", self.reset_any_file),
+            ("reset_new_level", self.reset_new_level),
+        ];
+        flags.extend(self.level_splits.flags());
+        flags
+    }
+
+    /**
+     * Sets one of the checkboxes from flags() by its key, returns false if the key doesn't exist
+     */
+    pub fn set_flag(&mut self, key: &str, value: bool) -> bool {
+        let flag = match key {
+            "fallback_load_removal" => &mut self.fallback_load_removal,
+            "start_enable" => &mut self.start_enable,
+            "start_new_file" => &mut self.start_new_file,
+            "start_any_file" => &mut self.start_any_file,
+            "start_new_il" => &mut self.start_new_il,
+            "start_exit_level" => &mut self.start_exit_level,
+            "splits_enable" => &mut self.splits_enable,
+            "splits_level_end" => &mut self.splits_level_end,
+            "splits_rooms" => &mut self.splits_rooms,
+            "reset_enable" => &mut self.reset_enable,
+            "reset_new_file" => &mut self.reset_new_file,
+            "reset_any_file" => &mut self.reset_any_file,
+            "reset_new_level" => &mut self.reset_new_level,
+            _ => return self.level_splits.set(key, value),
+        };
+        *flag = value;
+        true
+    }
 }
 
 impl Settings {
+    /**
+     * if the level end split of a level is picked, levels without a checkbox always split
+     */
+    pub fn level_split_enabled(&self, level: &Level) -> bool {
+        self.level_splits.is_enabled(level)
+    }
+
+    #[cfg(target_arch = "wasm32")]
     pub fn load_default_settings_for_mode(&mut self) {
-        print_message(&format!("Picked new mode: {:#?}", self.timer_mode.current));
+        logging::debug(&format!("Picked new mode: {:#?}", self.timer_mode.current));
 
         if !self.timer_mode_load_defaults {
             return;
@@ -163,3 +424,27 @@ impl Settings {
         settings_map.store();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_flag_can_be_set_back_from_its_key() {
+        let mut settings = Settings::default();
+        let flags: Vec<(String, bool)> = settings
+            .flags()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+
+        for (key, value) in &flags {
+            assert!(settings.set_flag(key, !value), "{key} can't be set");
+            assert!(
+                settings.flags().contains(&(key.as_str(), !value)),
+                "{key} wasn't set"
+            );
+        }
+        assert!(!settings.set_flag("not_a_setting", true));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::memory_fields::{decode_string, MemoryValues};
+
+/*
+Buffer documentation, the offsets of the watched values are in the memory_fields! table:
+0x00: magic numbers
+0x40: game version (string)
+0x80: file minutes (f64)
+0x88: file seconds (f64)
+0x90: level minute (f64)
+0x98: level seconds (f64)
+0xA0: current room (string)
+0xE0: end of level fade exists (bool / u8)
+0xE1: boss HP (u8)
+*/
+
+/// the start of the magic numbers at the start of the buffer, the bytes the buffer scan looks for.
+/// The game writes 32 of them but the other 16 aren't known, so they aren't read
+pub const MAGIC_PREFIX: [u8; 16] = [
+    0xC2, 0x5A, 0x17, 0x65, 0xBE, 0x4D, 0xDF, 0xD6, 0xF2, 0x1C, 0xD1, 0x3B, 0xA7, 0xA6, 0x1F, 0xC3,
+];
+
+/**
+ * false if the bytes of the whole buffer don't look like a buffer the game is writing to, like a stale copy of the magic numbers.
+ * The scan already matched the magic numbers, they are checked again for the reads of every tick after it, in case the game freed the buffer
+ */
+pub fn is_plausible(bytes: &[u8]) -> bool {
+    let sources = &MemoryValues::SOURCES;
+    let magic_matches = bytes.starts_with(&MAGIC_PREFIX);
+
+    let version_printable = sources
+        .game_version
+        .buffer_bytes::<String>(bytes)
+        .and_then(decode_string)
+        .is_some_and(|version| {
+            !version.is_empty()
+                && version
+                    .bytes()
+                    .all(|byte| byte.is_ascii_graphic() || byte == b' ')
+        });
+
+    let times_valid = [
+        &sources.file_minutes,
+        &sources.file_seconds,
+        &sources.level_minutes,
+        &sources.level_seconds,
+    ]
+    .into_iter()
+    .all(|source| {
+        source
+            .buffer_bytes::<f64>(bytes)
+            .and_then(|time| Some(f64::from_le_bytes(time.try_into().ok()?)))
+            .is_some_and(|time| time.is_finite() && time >= 0.0)
+    });
+
+    magic_matches && version_printable && times_valid
+}
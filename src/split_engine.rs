@@ -0,0 +1,575 @@
+use asr::{timer::TimerState, watcher::Pair};
+
+use crate::{
+    room_rules::{self, RoomTransitionRule},
+    rooms_ids::{self, Level},
+    settings::{Settings, TimerMode},
+    MemoryValues,
+};
+
+/**
+ * Something the splitter wants the timer to do, main() is the only place that applies these to livesplit
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimerAction {
+    Start,
+    Split,
+    Reset,
+    /// makes the livesplit game time frozen, if not used it stutters when the igt stops advancing
+    PauseGameTime,
+    /// lets the livesplit game time advance with real time again, for the load removal without the buffer
+    ResumeGameTime,
+    /// game time in seconds
+    SetGameTime(f64),
+}
+
+/**
+ * All the start, split and reset decisions, it never touches the timer directly so it can run outside of the wasm runtime
+ */
+#[derive(Default)]
+pub struct SplitEngine {
+    current_level: Level,
+    igt_file_secs_calculated: Pair<f64>,
+    igt_level_secs_calculated: Pair<f64>,
+
+    ng_plus_offset_seconds: Option<f64>,
+    iw_offset_seconds: Option<f64>,
+
+    // the level the last key room was in, only that level splits on being exited
+    unlocked_level: Option<Level>,
+    ctop_oob_split: bool, // should only happen once per run
+
+    last_room_split_name: String,
+    last_room_split_time: f64,
+
+    // why the last split happened, for the splitter status
+    last_split_reason: String,
+
+    transition_rules_text: String,
+    transition_rules: Vec<RoomTransitionRule>,
+}
+
+/**
+ * pushes an action and keeps track of the timer state it would cause, some checks later in the same tick depend on it
+ */
+fn emit(actions: &mut Vec<TimerAction>, timer_state: &mut TimerState, action: TimerAction) {
+    match action {
+        TimerAction::Start if *timer_state == TimerState::NotRunning => {
+            *timer_state = TimerState::Running
+        }
+        TimerAction::Reset => *timer_state = TimerState::NotRunning,
+        _ => {}
+    }
+    actions.push(action);
+}
+
+impl SplitEngine {
+    #[cfg(target_arch = "wasm32")]
+    pub fn current_level(&self) -> &Level {
+        &self.current_level
+    }
+
+    #[cfg(any(target_arch = "wasm32", test))]
+    pub fn last_split_reason(&self) -> &str {
+        &self.last_split_reason
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn transition_rules_text(&self) -> &str {
+        &self.transition_rules_text
+    }
+
+    /**
+     * parses the custom room transition rules again only if the text changed, returning how many were loaded or why they couldn't be.
+     * Invalid rules leave no rules loaded
+     */
+    pub fn update_transition_rules(&mut self, rules_text: &str) -> Option<Result<usize, String>> {
+        if rules_text == self.transition_rules_text {
+            return None;
+        }
+        self.transition_rules_text = rules_text.to_string();
+
+        match room_rules::parse_rules(rules_text) {
+            Ok(rules) => {
+                self.transition_rules = rules;
+                Some(Ok(self.transition_rules.len()))
+            }
+            Err(err) => {
+                self.transition_rules.clear();
+                Some(Err(err))
+            }
+        }
+    }
+
+    /**
+     * Takes the memory values of this tick and returns what the timer should do, in the order it should be done.
+     * `game_time_available` is false when the helper buffer wasn't found and the igt values are meaningless
+     */
+    pub fn update(
+        &mut self,
+        mem_values: &MemoryValues,
+        settings: &Settings,
+        timer_state: TimerState,
+        game_time_available: bool,
+    ) -> Vec<TimerAction> {
+        let mut actions = Vec::new();
+        let mut timer_state = timer_state;
+
+        self.igt_file_secs_calculated.old = self.igt_file_secs_calculated.current;
+        self.igt_file_secs_calculated.current =
+            mem_values.file_minutes.current * 60.0 + mem_values.file_seconds.current;
+        self.igt_level_secs_calculated.old = self.igt_level_secs_calculated.current;
+        self.igt_level_secs_calculated.current =
+            mem_values.level_minutes.current * 60.0 + mem_values.level_seconds.current;
+
+        // update current level and enable full game splits
+        if mem_values.room_name.changed() {
+            self.current_level =
+                rooms_ids::get_current_level(&mem_values.room_name.current, self.current_level);
+            if let Some(level) = rooms_ids::get_unlocked_level(&mem_values.room_name.current) {
+                self.unlocked_level = Some(level);
+            }
+        }
+
+        // offsets for ng+ and iw
+        if timer_state == TimerState::NotRunning {
+            // ng+ offset update
+            if self.ng_plus_offset_seconds.is_none()
+                && mem_values.room_name.current == "tower_entrancehall"
+                && mem_values.level_minutes.current == 0.0
+                && mem_values.level_seconds.current < 1.0
+            {
+                self.ng_plus_offset_seconds = Some(self.igt_file_secs_calculated.current);
+            }
+            if self.ng_plus_offset_seconds.is_some()
+                && (mem_values.room_name.current == "hub_loadingscreen"
+                    || mem_values.room_name.current == "Finalintro")
+            {
+                self.ng_plus_offset_seconds = None;
+            }
+
+            // iw offset update
+            if self.iw_offset_seconds.is_none() && self.current_level == Level::Hub {
+                self.iw_offset_seconds = Some(self.igt_file_secs_calculated.current);
+            }
+            if self.iw_offset_seconds.is_some() && self.current_level != Level::Hub {
+                self.iw_offset_seconds = None;
+            }
+        }
+
+        // game time set
+        if game_time_available {
+            emit(&mut actions, &mut timer_state, TimerAction::PauseGameTime);
+
+            let game_time_livesplit = match settings.timer_mode.current {
+                TimerMode::FullGame => self.igt_file_secs_calculated.current,
+                TimerMode::IL => self.igt_level_secs_calculated.current,
+                TimerMode::NewGamePlus => {
+                    self.igt_file_secs_calculated.current
+                        - self.ng_plus_offset_seconds.unwrap_or(0.0)
+                }
+                TimerMode::IW => {
+                    self.igt_level_secs_calculated.current - self.iw_offset_seconds.unwrap_or(0.0)
+                }
+            };
+            emit(
+                &mut actions,
+                &mut timer_state,
+                TimerAction::SetGameTime(game_time_livesplit),
+            );
+        } else if settings.fallback_load_removal {
+            // real time with loads removed, only the menus and loading screens can be told apart without the buffer.
+            // the door fades between rooms are gameplay time for the splitter, and no name means the game isn't in a room yet
+            let loading = rooms_ids::is_loading_room(&mem_values.room_name.current)
+                || mem_values.room_name.current.is_empty();
+            let action = if loading {
+                TimerAction::PauseGameTime
+            } else {
+                TimerAction::ResumeGameTime
+            };
+            emit(&mut actions, &mut timer_state, action);
+        }
+
+        // start
+        if settings.start_enable {
+            if settings.start_new_file
+                && mem_values.room_name.current == "tower_entrancehall"
+                && mem_values.room_name.old == "Finalintro"
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Start);
+            }
+            if settings.start_any_file
+                && mem_values.room_name.current == "tower_entrancehall"
+                && mem_values.room_name.old == "hub_loadingscreen"
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Start);
+            }
+            if settings.start_new_il
+                && rooms_ids::get_starting_room(&self.current_level) == mem_values.room_name.current
+                && self.igt_level_secs_calculated.current > 0.07
+                && self.igt_level_secs_calculated.current <= 0.1
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Start);
+            }
+            if settings.start_exit_level
+                && mem_values.room_name.changed()
+                && rooms_ids::full_game_split_rooms(&mem_values.room_name.old)
+                && self.current_level == Level::Hub
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Start);
+            }
+        }
+
+        // reset
+        if settings.reset_enable {
+            if settings.reset_new_file
+                && mem_values.room_name.current == "Finalintro"
+                && mem_values.room_name.old != "Finalintro"
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Reset);
+            }
+            if settings.reset_any_file
+                && mem_values.room_name.changed()
+                && mem_values.room_name.current == "hub_loadingscreen"
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Reset);
+            }
+            if settings.reset_new_level
+                && self.igt_level_secs_calculated.decreased()
+                && self.current_level != Level::Hub
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Reset);
+            }
+        }
+
+        // split
+        if settings.splits_enable {
+            // covers any full game split
+            if settings.splits_level_end {
+                // standard level / boss end
+                if mem_values.room_name.changed()
+                    && (self.current_level == Level::Hub
+                        || self.current_level == Level::ResultsScreen)
+                {
+                    if let Some(exited) = rooms_ids::get_exited_level(&mem_values.room_name.old)
+                        .filter(|exited| self.unlocked_level == Some(exited.level))
+                    {
+                        // bosses only end once their hp is gone, the hp stays in memory after leaving them so other levels ignore it.
+                        // got lazy and hardcoded the noise pizzaface split here :)
+                        let level_ended = !exited.boss
+                            || mem_values.boss_hp.old == 0
+                            || (mem_values.room_name.current == "boss_pizzafacehub"
+                                && mem_values.room_name.old == "boss_pizzaface");
+                        if level_ended {
+                            // an unpicked level still uses up the unlock, so it can't leak to the next level
+                            if settings.level_split_enabled(&exited.level) {
+                                emit(&mut actions, &mut timer_state, TimerAction::Split);
+                                self.last_split_reason = format!("Level end: {:?}", exited.level);
+                            }
+                            self.unlocked_level = None;
+                        }
+                    }
+                }
+
+                // end of the run frame perfect split, technically the prev "if" could cover this too but frame perfectly splitting at the end is cooler
+                if mem_values.end_of_level.current
+                    && !mem_values.end_of_level.old
+                    && mem_values.room_name.current == "tower_entrancehall"
+                    && settings.level_split_enabled(&Level::F5CrumblingTower)
+                {
+                    emit(&mut actions, &mut timer_state, TimerAction::Split);
+                    self.last_split_reason = "End of the run".to_string();
+                }
+
+                // ctop entering from oob
+                if timer_state == TimerState::NotRunning && self.ctop_oob_split {
+                    self.ctop_oob_split = false;
+                }
+                // it takes the place of the pizzaface split
+                if mem_values.room_name.current == "tower_finalhallway"
+                    && mem_values.room_name.old == "tower_5"
+                    && !self.ctop_oob_split
+                {
+                    self.ctop_oob_split = true;
+                    if settings.level_split_enabled(&Level::PizzaFace) {
+                        emit(&mut actions, &mut timer_state, TimerAction::Split);
+                        self.last_split_reason = "CTOP entered out of bounds".to_string();
+                    }
+                }
+            }
+
+            if settings.splits_rooms
+                && (self.igt_level_secs_calculated.current - self.last_room_split_time > 2.0
+                    || mem_values.room_name.current != self.last_room_split_name)
+                && (mem_values.room_name.changed()
+                    || mem_values.end_of_level.current && mem_values.end_of_level.old)
+            {
+                self.last_room_split_time = self.igt_level_secs_calculated.current;
+                self.last_room_split_name = mem_values.room_name.old.clone();
+
+                emit(&mut actions, &mut timer_state, TimerAction::Split);
+                self.last_split_reason = format!(
+                    "Room change: {} -> {}",
+                    mem_values.room_name.old, mem_values.room_name.current
+                );
+            }
+
+            // custom transitions, unless something else already split on this room change
+            if mem_values.room_name.changed()
+                && !actions.contains(&TimerAction::Split)
+                && self.transition_rules.iter().any(|rule| {
+                    rule.matches(&mem_values.room_name.old, &mem_values.room_name.current)
+                })
+            {
+                emit(&mut actions, &mut timer_state, TimerAction::Split);
+                self.last_split_reason = format!(
+                    "Transition rule: {} -> {}",
+                    mem_values.room_name.old, mem_values.room_name.current
+                );
+            }
+        }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<T>(pair: &mut Pair<T>, value: T) {
+        pair.old = core::mem::replace(&mut pair.current, value);
+    }
+
+    fn hold<T: Clone>(pair: &mut Pair<T>) {
+        pair.old.clone_from(&pair.current);
+    }
+
+    /**
+     * feeds ticks to the engine and keeps the timer state the way livesplit would
+     */
+    struct Run {
+        engine: SplitEngine,
+        mem_values: MemoryValues,
+        settings: Settings,
+        timer_state: TimerState,
+    }
+
+    impl Run {
+        fn new() -> Self {
+            Self {
+                engine: SplitEngine::default(),
+                mem_values: MemoryValues::default(),
+                settings: Settings::default(),
+                timer_state: TimerState::NotRunning,
+            }
+        }
+
+        /**
+         * one tick with the changes, every value that isn't changed stays the same as the last tick.
+         * Only the start, split and reset actions are returned
+         */
+        fn tick(&mut self, change: impl FnOnce(&mut MemoryValues)) -> Vec<TimerAction> {
+            let values = &mut self.mem_values;
+            hold(&mut values.room_id);
+            hold(&mut values.room_name);
+            hold(&mut values.level_minutes);
+            hold(&mut values.level_seconds);
+            hold(&mut values.end_of_level);
+            hold(&mut values.boss_hp);
+            change(values);
+
+            let actions =
+                self.engine
+                    .update(&self.mem_values, &self.settings, self.timer_state, true);
+            for action in &actions {
+                match action {
+                    TimerAction::Start if self.timer_state == TimerState::NotRunning => {
+                        self.timer_state = TimerState::Running
+                    }
+                    TimerAction::Reset => self.timer_state = TimerState::NotRunning,
+                    _ => {}
+                }
+            }
+            actions
+                .into_iter()
+                .filter(|action| {
+                    matches!(
+                        action,
+                        TimerAction::Start | TimerAction::Split | TimerAction::Reset
+                    )
+                })
+                .collect()
+        }
+
+        fn enter(&mut self, room_name: &str) -> Vec<TimerAction> {
+            self.tick(|values| {
+                let room_id = values.room_id.current + 1;
+                set(&mut values.room_id, room_id);
+                set(&mut values.room_name, room_name.to_string());
+            })
+        }
+    }
+
+    #[test]
+    fn pizzaface_splits_on_leaving_to_its_hub_with_hp_left() {
+        let mut run = Run::new();
+        run.enter("tower_5");
+        assert_eq!(run.enter("boss_pizzaface"), []);
+        run.tick(|values| set(&mut values.boss_hp, 4));
+
+        assert!(run.mem_values.boss_hp.current != 0);
+        assert_eq!(run.enter("boss_pizzafacehub"), [TimerAction::Split]);
+        assert_eq!(run.engine.last_split_reason(), "Level end: PizzaFace");
+    }
+
+    #[test]
+    fn boss_doesnt_split_when_left_with_hp() {
+        let mut run = Run::new();
+        run.enter("tower_1");
+        run.enter("boss_pepperman");
+        run.tick(|values| set(&mut values.boss_hp, 4));
+
+        assert_eq!(run.enter("tower_1"), []);
+    }
+
+    #[test]
+    fn level_splits_with_hp_left_from_a_boss() {
+        let mut run = Run::new();
+        run.enter("tower_1");
+        run.enter("boss_pepperman");
+        run.tick(|values| set(&mut values.boss_hp, 4));
+        run.enter("tower_1");
+
+        run.enter("entrance_1");
+        run.enter("entrance_10");
+        run.enter("entrance_1");
+        assert_eq!(run.enter("tower_1"), [TimerAction::Split]);
+        assert_eq!(run.engine.last_split_reason(), "Level end: F1JohnGutter");
+    }
+
+    #[test]
+    fn a_boss_left_with_hp_doesnt_unlock_the_next_level() {
+        let mut run = Run::new();
+        run.enter("tower_1");
+        run.enter("boss_pepperman");
+        run.tick(|values| set(&mut values.boss_hp, 4));
+        run.enter("tower_1");
+
+        // entrance_10 is never reached, so leaving the level isn't its end
+        run.enter("entrance_1");
+        assert_eq!(run.enter("tower_1"), []);
+    }
+
+    #[test]
+    fn unpicked_level_doesnt_split() {
+        let mut run = Run::new();
+        assert!(run.settings.set_flag("split_F1JohnGutter", false));
+        run.enter("tower_1");
+        run.enter("entrance_1");
+        run.enter("entrance_10");
+        run.enter("entrance_1");
+
+        assert_eq!(run.enter("tower_1"), []);
+    }
+
+    #[test]
+    fn transition_rules_split_once_loaded() {
+        let mut run = Run::new();
+        assert_eq!(
+            run.engine
+                .update_transition_rules("entrance_1 -> entrance_*"),
+            Some(Ok(1))
+        );
+        run.enter("entrance_1");
+
+        assert_eq!(run.enter("entrance_2"), [TimerAction::Split]);
+        assert_eq!(
+            run.engine.last_split_reason(),
+            "Transition rule: entrance_1 -> entrance_2"
+        );
+        assert_eq!(run.enter("entrance_3"), []);
+    }
+
+    #[test]
+    fn fallback_only_pauses_outside_of_rooms_and_in_loading_screens() {
+        let mut engine = SplitEngine::default();
+        let settings = Settings::default();
+        let mut values = MemoryValues::default();
+        let mut paused = |values: &mut MemoryValues, room_name: &str| {
+            let room_id = values.room_id.current + 1;
+            set(&mut values.room_id, room_id);
+            set(&mut values.room_name, room_name.to_string());
+            let actions = engine.update(values, &settings, TimerState::Running, false);
+            actions.contains(&TimerAction::PauseGameTime)
+        };
+
+        assert!(paused(&mut values, ""));
+        assert!(paused(&mut values, "hub_loadingscreen"));
+        // room changes don't take any time off
+        assert!(!paused(&mut values, "tower_1"));
+        assert!(!paused(&mut values, "entrance_1"));
+        assert!(paused(&mut values, "Mainmenu"));
+    }
+
+    #[test]
+    fn ctop_out_of_bounds_splits_once_until_reset() {
+        let mut run = Run::new();
+        run.enter("Finalintro");
+        assert_eq!(run.enter("tower_entrancehall"), [TimerAction::Start]);
+
+        run.enter("tower_5");
+        assert_eq!(run.enter("tower_finalhallway"), [TimerAction::Split]);
+        assert_eq!(run.engine.last_split_reason(), "CTOP entered out of bounds");
+        run.enter("tower_5");
+        assert_eq!(run.enter("tower_finalhallway"), []);
+
+        // a new file resets the timer, which arms the split again
+        assert_eq!(run.enter("Finalintro"), [TimerAction::Reset]);
+        assert_eq!(run.enter("tower_entrancehall"), [TimerAction::Start]);
+        run.enter("tower_5");
+        assert_eq!(run.enter("tower_finalhallway"), [TimerAction::Split]);
+    }
+
+    #[test]
+    fn starts_on_a_new_file() {
+        let mut run = Run::new();
+        run.enter("Mainmenu");
+        run.enter("Finalintro");
+
+        assert_eq!(run.enter("tower_entrancehall"), [TimerAction::Start]);
+        assert_eq!(run.timer_state, TimerState::Running);
+        assert_eq!(run.tick(|_| {}), []);
+    }
+
+    #[test]
+    fn doesnt_start_on_loading_a_file_by_default() {
+        let mut run = Run::new();
+        run.enter("hub_loadingscreen");
+
+        assert_eq!(run.enter("tower_entrancehall"), []);
+    }
+
+    #[test]
+    fn resets_on_restarting_a_level() {
+        let mut run = Run::new();
+        run.enter("tower_1");
+        run.enter("entrance_1");
+        run.tick(|values| set(&mut values.level_seconds, 12.5));
+        assert_eq!(run.tick(|values| set(&mut values.level_seconds, 13.0)), []);
+
+        assert_eq!(
+            run.tick(|values| set(&mut values.level_seconds, 0.0)),
+            [TimerAction::Reset]
+        );
+    }
+
+    #[test]
+    fn doesnt_reset_on_a_level_timer_going_back_in_the_hub() {
+        let mut run = Run::new();
+        run.enter("tower_1");
+        run.tick(|values| set(&mut values.level_seconds, 12.5));
+
+        assert_eq!(run.tick(|values| set(&mut values.level_seconds, 0.0)), []);
+    }
+}
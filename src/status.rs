@@ -0,0 +1,140 @@
+use crate::{
+    connection::ConnectionStage,
+    logging,
+    memory_error::{MemoryError, Recovery},
+    settings::{LogLevel, TimerMode},
+    variables, MemoryAddresses,
+};
+
+/**
+ * Everything a runner needs to see why the splitter isn't doing something, in one place.
+ * Each part is only formatted and pushed to its timer variable when it changes
+ */
+#[derive(Default)]
+pub struct SplitterStatus {
+    stage: Option<ConnectionStage>,
+    // room id, room names array and buffer
+    resolved: Option<(bool, bool, bool)>,
+    mode: Option<&'static str>,
+    game_version: Option<String>,
+    timer_mode: Option<TimerMode>,
+    last_split: Option<String>,
+    last_error: Option<String>,
+}
+
+impl SplitterStatus {
+    /**
+     * shows every variable once so they are all there before the game is found
+     */
+    pub fn new() -> Self {
+        let mut status = Self::default();
+        variables::set("Splitter Status", "Not attached");
+        status.set_addresses(&MemoryAddresses::default(), false);
+        status.set_game_version("");
+        status.set_last_split("");
+        variables::set("Last Error", "-");
+        status.last_error = Some(String::new());
+        status
+    }
+
+    pub fn set_stage(&mut self, stage: ConnectionStage) {
+        stage.announce();
+        if self.stage != Some(stage) {
+            self.stage = Some(stage);
+            variables::set("Splitter Status", format_args!("{stage:?}"));
+        }
+    }
+
+    /**
+     * which addresses are found and with them if the buffer or the room names array is used, and how the time is kept without the buffer
+     */
+    pub fn set_addresses(&mut self, addresses: &MemoryAddresses, fallback_load_removal: bool) {
+        let resolved = (
+            addresses.room_id.is_some(),
+            addresses.room_names.is_some(),
+            addresses.buffer_helper.is_some(),
+        );
+        let (room_id, room_names, buffer) = resolved;
+
+        let mode = if buffer {
+            "Buffer"
+        } else if room_names && fallback_load_removal {
+            "Fallback (real time without loads)"
+        } else if room_names {
+            "Fallback (real time)"
+        } else {
+            "-"
+        };
+        if self.mode != Some(mode) {
+            self.mode = Some(mode);
+            variables::set("Mode", mode);
+        }
+
+        if self.resolved == Some(resolved) {
+            return;
+        }
+        self.resolved = Some(resolved);
+
+        let found: Vec<&str> = [
+            (room_id, "room ID"),
+            (room_names, "room names array"),
+            (buffer, "buffer"),
+        ]
+        .iter()
+        .filter(|(is_found, _)| *is_found)
+        .map(|(_, name)| *name)
+        .collect();
+        variables::set(
+            "Addresses Found",
+            &if found.is_empty() {
+                "-".to_string()
+            } else {
+                found.join(", ")
+            },
+        );
+    }
+
+    pub fn set_game_version(&mut self, game_version: &str) {
+        if self.game_version.as_deref() != Some(game_version) {
+            self.game_version = Some(game_version.to_string());
+            variables::set(
+                "Game Version",
+                if game_version.is_empty() {
+                    "-"
+                } else {
+                    game_version
+                },
+            );
+        }
+    }
+
+    pub fn set_timer_mode(&mut self, timer_mode: TimerMode) {
+        if self.timer_mode != Some(timer_mode) {
+            self.timer_mode = Some(timer_mode);
+            variables::set("Timer Mode", format_args!("{timer_mode:?}"));
+        }
+    }
+
+    pub fn set_last_split(&mut self, reason: &str) {
+        if self.last_split.as_deref() != Some(reason) {
+            self.last_split = Some(reason.to_string());
+            variables::set("Last Split", if reason.is_empty() { "-" } else { reason });
+        }
+    }
+
+    /**
+     * logs the error and shows it as the latest one, the ones the splitter recovers from are only warnings
+     */
+    pub fn report_error(&mut self, error: &MemoryError) {
+        let message = error.to_string();
+        let level = match error.recovery() {
+            Recovery::Detach => LogLevel::Error,
+            Recovery::RetryRead | Recovery::Rescan(_) => LogLevel::Warn,
+        };
+        logging::log(level, &message);
+        if self.last_error.as_deref() != Some(message.as_str()) {
+            variables::set("Last Error", &message);
+            self.last_error = Some(message);
+        }
+    }
+}
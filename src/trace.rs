@@ -0,0 +1,339 @@
+use asr::{timer::TimerState, watcher::Pair};
+
+use crate::{
+    settings::{Settings, TimerMode},
+    split_engine::{SplitEngine, TimerAction},
+    MemoryValues,
+};
+
+/*
+Trace format, one line per group of identical ticks, every line starts with the marker so traces can be cut from a full log.
+A trace starts when the splitter attaches to the game, where the split engine starts, so replaying starts from a new engine too.
+The buffer line comes again when the buffer is found or lost while recording, settings changed while recording aren't in the trace:
+PTT # format <TRACE_FORMAT_VERSION>
+PTT # buffer <true/false>
+PTT # mode <TimerMode>
+PTT # setting <key> <true/false>
+PTT # transition_rules <rules>
+PTT # columns <field> <field> ...
+PTT <repeated ticks>\t<value of the first column>\t<value of the second column>...
+the columns are the names of the MemoryValues fields, in the order of the memory_fields! table. Replaying sets the fields by name,
+so a trace from before a field was added still replays with the default value of that field
+*/
+const TRACE_MARKER: &str = "PTT ";
+/// goes up when the header changes, traces from before the format line are version 1
+const TRACE_FORMAT_VERSION: u32 = 2;
+/// version 1 traces have no columns line, they always had these
+const FORMAT_1_COLUMNS: [&str; 9] = [
+    "room_id",
+    "file_minutes",
+    "file_seconds",
+    "level_minutes",
+    "level_seconds",
+    "end_of_level",
+    "boss_hp",
+    "game_version",
+    "room_name",
+];
+
+/**
+ * the header of a trace, it has what is needed to run the split logic the same way it ran while recording
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn header(settings: &Settings, transition_rules: &str, buffer_found: bool) -> String {
+    let mut header = format!("{TRACE_MARKER}# format {TRACE_FORMAT_VERSION}\n");
+    header.push_str(&format!("{TRACE_MARKER}# buffer {buffer_found}\n"));
+    header.push_str(&format!(
+        "{TRACE_MARKER}# mode {:?}",
+        settings.timer_mode.current
+    ));
+    for (key, value) in settings.flags() {
+        header.push_str(&format!("\n{TRACE_MARKER}# setting {key} {value}"));
+    }
+    // always on one line, new lines and commas separate rules the same way
+    header.push_str(&format!(
+        "\n{TRACE_MARKER}# transition_rules {}",
+        transition_rules.replace('\n', ",")
+    ));
+    header.push_str(&format!(
+        "\n{TRACE_MARKER}# columns {}",
+        MemoryValues::FIELD_NAMES.join(" ")
+    ));
+    header
+}
+
+/**
+ * Turns the memory values of every tick into trace lines, identical ticks are merged in one line
+ */
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Default)]
+pub struct TraceRecorder {
+    last_tick: Option<String>,
+    repeats: u32,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl TraceRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.last_tick.is_some()
+    }
+
+    /**
+     * returns the finished line of the previous ticks once the values change
+     */
+    pub fn record(&mut self, mem_values: &MemoryValues) -> Option<String> {
+        let mut tick = String::new();
+        mem_values.write_trace(&mut tick);
+
+        if self.last_tick.as_ref() == Some(&tick) {
+            self.repeats += 1;
+            return None;
+        }
+
+        let finished_line = self.finish();
+        self.last_tick = Some(tick);
+        self.repeats = 1;
+        finished_line
+    }
+
+    /**
+     * returns the line still being recorded, call it when recording stops
+     */
+    pub fn finish(&mut self) -> Option<String> {
+        let last_tick = self.last_tick.take()?;
+        let repeats = core::mem::take(&mut self.repeats);
+        (repeats > 0).then(|| format!("{TRACE_MARKER}{repeats}\t{last_tick}"))
+    }
+
+    /**
+     * the lines for the buffer being found or lost while recording, the trace goes on and replaying it switches on the same tick
+     */
+    pub fn buffer_changed(&mut self, buffer_found: bool) -> Option<String> {
+        let last_tick = self.last_tick.as_ref()?;
+        let mut lines = String::new();
+        if self.repeats > 0 {
+            lines = format!("{TRACE_MARKER}{}\t{last_tick}\n", self.repeats);
+        }
+        self.repeats = 0;
+        lines.push_str(&format!("{TRACE_MARKER}# buffer {buffer_found}"));
+        Some(lines)
+    }
+}
+
+/**
+ * splits a tick line without the marker in how many times it repeats and the columns of its values
+ */
+fn parse_tick(line: &str) -> Option<(u32, &str)> {
+    let (repeats, columns) = line.split_once('\t')?;
+    Some((repeats.parse().ok()?, columns))
+}
+
+fn parse_timer_mode(name: &str) -> Option<TimerMode> {
+    match name {
+        "FullGame" => Some(TimerMode::FullGame),
+        "IL" => Some(TimerMode::IL),
+        "NewGamePlus" => Some(TimerMode::NewGamePlus),
+        "IW" => Some(TimerMode::IW),
+        _ => None,
+    }
+}
+
+/**
+ * Feeds a recorded trace through the split logic, returns every timer action with the tick it happened on.
+ * Lines without the trace marker are ignored so a whole log can be passed
+ */
+pub fn replay(trace: &str) -> Result<Vec<(usize, TimerAction)>, String> {
+    let mut settings = Settings::default();
+    let mut buffer_found = false;
+    let mut columns = FORMAT_1_COLUMNS.to_vec();
+
+    let mut mem_values = MemoryValues::default();
+    let mut split_engine = SplitEngine::default();
+    let mut timer_state = TimerState::NotRunning;
+
+    let mut tick = 0;
+    let mut actions = Vec::new();
+
+    for (line_index, line) in trace.lines().enumerate() {
+        let Some(marker_index) = line.find(TRACE_MARKER) else {
+            continue;
+        };
+        let line = &line[marker_index + TRACE_MARKER.len()..];
+        let line_error = || format!("Line {}: could not parse \"{line}\"", line_index + 1);
+
+        if let Some(rules) = line.strip_prefix("# transition_rules") {
+            if let Some(Err(err)) = split_engine.update_transition_rules(rules.trim()) {
+                return Err(format!("Line {}: {err}", line_index + 1));
+            }
+            continue;
+        }
+
+        if let Some(names) = line.strip_prefix("# columns ") {
+            columns = names.split(' ').collect();
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix("# ") {
+            let mut words = header.split(' ');
+            match (words.next(), words.next(), words.next()) {
+                (Some("format"), Some(version), None) => {
+                    let version: u32 = version.parse().map_err(|_| line_error())?;
+                    if version > TRACE_FORMAT_VERSION {
+                        return Err(format!(
+                            "Line {}: trace format {version} can't be replayed, only formats up to {TRACE_FORMAT_VERSION} can",
+                            line_index + 1
+                        ));
+                    }
+                }
+                (Some("buffer"), Some(value), None) => {
+                    buffer_found = value.parse().map_err(|_| line_error())?;
+                }
+                (Some("mode"), Some(mode), None) => {
+                    let mode = parse_timer_mode(mode).ok_or_else(line_error)?;
+                    settings.timer_mode = Pair {
+                        old: mode,
+                        current: mode,
+                    };
+                }
+                (Some("setting"), Some(key), Some(value)) => {
+                    let value = value.parse().map_err(|_| line_error())?;
+                    if !settings.set_flag(key, value) {
+                        return Err(line_error());
+                    }
+                }
+                _ => return Err(line_error()),
+            }
+            continue;
+        }
+
+        let (repeats, values) = parse_tick(line).ok_or_else(line_error)?;
+        for _ in 0..repeats {
+            let mut values = values.splitn(columns.len(), '\t');
+            for column in &columns {
+                mem_values
+                    .set_from_trace(column, values.next().ok_or_else(line_error)?)
+                    .ok_or_else(line_error)?;
+            }
+
+            for action in split_engine.update(&mem_values, &settings, timer_state, buffer_found) {
+                match action {
+                    TimerAction::Start if timer_state == TimerState::NotRunning => {
+                        timer_state = TimerState::Running
+                    }
+                    TimerAction::Reset => timer_state = TimerState::NotRunning,
+                    _ => {}
+                }
+                actions.push((tick, action));
+            }
+            tick += 1;
+        }
+    }
+
+    Ok(actions)
+}
+
+/**
+ * replay() made readable, the game time and its pausing are only listed when they change so the output isn't one line per tick
+ */
+pub fn replay_report(trace: &str) -> Result<Vec<String>, String> {
+    let mut report = Vec::new();
+    let mut last_game_time = None;
+    let mut last_pause_action = None;
+
+    for (tick, action) in replay(trace)? {
+        match action {
+            TimerAction::PauseGameTime | TimerAction::ResumeGameTime => {
+                if last_pause_action == Some(action) {
+                    continue;
+                }
+                last_pause_action = Some(action);
+            }
+            TimerAction::SetGameTime(seconds) if last_game_time == Some(seconds) => continue,
+            TimerAction::SetGameTime(seconds) => last_game_time = Some(seconds),
+            _ => {}
+        }
+        report.push(format!("tick {tick}: {action:?}"));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_fields::set_pair;
+
+    fn tick(room_id: i32, room_name: &str, values: &mut MemoryValues) {
+        set_pair(&mut values.room_id, room_id);
+        set_pair(&mut values.game_version, "v1.1.063".to_string());
+        set_pair(&mut values.room_name, room_name.to_string());
+    }
+
+    /**
+     * A new file, the tower and john gutter up to its escape back to the tower. Recording these ticks has to give
+     * tests/traces/entrance_full_game.txt, the file is what a recording of them looks like
+     */
+    #[test]
+    fn the_checked_in_trace_is_what_recording_writes() {
+        // repeats, room id, file seconds, level seconds, end fade, room name
+        let ticks = [
+            (3, 5, 0.0, 0.0, false, "Mainmenu"),
+            (2, 6, 0.0, 0.0, false, "Finalintro"),
+            (2, 7, 0.5, 0.0, false, "tower_entrancehall"),
+            (2, 8, 4.0, 0.0, false, "tower_1"),
+            (2, 9, 6.0, 0.5, false, "entrance_1"),
+            (2, 10, 20.0, 14.0, false, "entrance_10"),
+            (2, 9, 25.0, 19.0, true, "entrance_1"),
+            (3, 8, 26.0, 19.0, true, "tower_1"),
+        ];
+        let mut values = MemoryValues::default();
+        let mut recorder = TraceRecorder::default();
+        let mut trace = header(&Settings::default(), "", true);
+        for (repeats, room_id, file_seconds, level_seconds, end_of_level, room_name) in ticks {
+            for _ in 0..repeats {
+                tick(room_id, room_name, &mut values);
+                set_pair(&mut values.file_seconds, file_seconds);
+                set_pair(&mut values.level_seconds, level_seconds);
+                set_pair(&mut values.end_of_level, end_of_level);
+                if let Some(line) = recorder.record(&values) {
+                    trace.push_str(&format!("\n{line}"));
+                }
+            }
+        }
+        trace.push_str(&format!("\n{}\n", recorder.finish().unwrap()));
+
+        assert_eq!(
+            trace,
+            include_str!("../tests/traces/entrance_full_game.txt")
+        );
+    }
+
+    #[test]
+    fn a_buffer_found_while_recording_goes_on_in_the_same_trace() {
+        let mut values = MemoryValues::default();
+        let mut recorder = TraceRecorder::default();
+        assert_eq!(recorder.buffer_changed(true), None);
+
+        tick(5, "Mainmenu", &mut values);
+        assert_eq!(recorder.record(&values), None);
+        assert_eq!(recorder.record(&values), None);
+        assert_eq!(
+            recorder.buffer_changed(true).unwrap(),
+            "PTT 2\t5\t0\t0\t0\t0\t0\t0\tv1.1.063\tMainmenu\nPTT # buffer true"
+        );
+        assert!(recorder.is_recording());
+
+        assert_eq!(recorder.record(&values), None);
+        assert_eq!(
+            recorder.finish().unwrap(),
+            "PTT 1\t5\t0\t0\t0\t0\t0\t0\tv1.1.063\tMainmenu"
+        );
+    }
+
+    #[test]
+    fn format_1_traces_still_have_fields_for_their_columns() {
+        for column in FORMAT_1_COLUMNS {
+            assert!(MemoryValues::FIELD_NAMES.contains(&column), "{column}");
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fmt::Write, sync::Mutex};
+
+#[cfg(target_arch = "wasm32")]
+use asr::timer::set_variable;
+
+/**
+ * A timer variable and the value it was last given
+ */
+struct Variable {
+    name: String,
+    value: String,
+    debug: bool,
+}
+
+/**
+ * the tests have no timer, the values are only kept here
+ */
+#[cfg(not(target_arch = "wasm32"))]
+fn set_variable(_name: &str, _value: &str) {}
+
+/**
+ * Every timer variable goes through here so the host only hears about the ones that changed.
+ * Debug variables are raw values like addresses and the float minutes and seconds, they can be hidden in the settings
+ */
+struct Variables {
+    show_debug: bool,
+    // reused to format new values without allocating every tick
+    scratch: String,
+    shown: Vec<Variable>,
+}
+
+static VARIABLES: Mutex<Variables> = Mutex::new(Variables {
+    show_debug: true,
+    scratch: String::new(),
+    shown: Vec::new(),
+});
+
+fn publish(name: &str, value: impl core::fmt::Display, debug: bool) {
+    let Ok(mut variables) = VARIABLES.lock() else {
+        return;
+    };
+    let Variables {
+        show_debug,
+        scratch,
+        shown,
+    } = &mut *variables;
+
+    scratch.clear();
+    let _ = write!(scratch, "{value}");
+
+    let variable = match shown.iter().position(|variable| variable.name == name) {
+        Some(index) if shown[index].value == *scratch => return,
+        Some(index) => &mut shown[index],
+        None => {
+            shown.push(Variable {
+                name: name.to_string(),
+                value: String::new(),
+                debug,
+            });
+            shown.last_mut().unwrap()
+        }
+    };
+    variable.value.clone_from(scratch);
+    // hidden ones still keep their value, to show it if they are turned back on
+    if !variable.debug || *show_debug {
+        set_variable(name, &variable.value);
+    }
+}
+
+/**
+ * sets a variable meant for the runner, only if the value changed
+ */
+pub fn set(name: &str, value: impl core::fmt::Display) {
+    publish(name, value, false);
+}
+
+/**
+ * sets a raw value that's only useful for debugging, only if the value changed and debug variables are shown
+ */
+pub fn set_debug(name: &str, value: impl core::fmt::Display) {
+    publish(name, value, true);
+}
+
+/**
+ * Shows or hides the debug variables. The ones already shown are emptied when hiding them
+ */
+#[cfg(target_arch = "wasm32")]
+pub fn set_show_debug(show: bool) {
+    let Ok(mut variables) = VARIABLES.lock() else {
+        return;
+    };
+    if variables.show_debug == show {
+        return;
+    }
+    variables.show_debug = show;
+
+    for variable in variables.shown.iter().filter(|variable| variable.debug) {
+        set_variable(&variable.name, if show { &variable.value } else { "" });
+    }
+}
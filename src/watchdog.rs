@@ -0,0 +1,82 @@
+use crate::{rooms_ids, MemoryValues};
+
+// ticks at the main loop tick rate before each check decides the addresses are stale
+const INVALID_BUFFER_TICKS: u32 = 5;
+const UNKNOWN_ROOM_TICKS: u32 = 240 * 10;
+// the igt always moves between two rooms of the hub or a level, but not in menus, loading screens or the results screen
+const ROOM_CHANGES_WITHOUT_IGT: u32 = 4;
+
+/**
+ * Which address needs to be found again
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StaleMemory {
+    Buffer,
+    RoomNames,
+}
+
+/**
+ * Notices when the values read every tick stop making sense, like when the game reallocates the buffer or moves the room names array.
+ * The reads still work in that case so refresh_mem_values can't tell
+ */
+#[derive(Default)]
+pub struct MemoryWatchdog {
+    invalid_buffer_ticks: u32,
+    unknown_room_ticks: u32,
+    room_changes_without_igt: u32,
+    last_file_igt: f64,
+}
+
+impl MemoryWatchdog {
+    /**
+     * checks the values of this tick, returns what to look for again. The checks start over after that
+     */
+    pub fn check(&mut self, mem_values: &MemoryValues, buffer_mode: bool) -> Option<StaleMemory> {
+        let stale = self.find_stale(mem_values, buffer_mode);
+        if stale.is_some() {
+            *self = Self::default();
+        }
+        stale
+    }
+
+    fn find_stale(&mut self, mem_values: &MemoryValues, buffer_mode: bool) -> Option<StaleMemory> {
+        // the magic numbers or the rest of the buffer are wrong
+        if buffer_mode && !mem_values.buffer_valid {
+            self.invalid_buffer_ticks += 1;
+            if self.invalid_buffer_ticks >= INVALID_BUFFER_TICKS {
+                return Some(StaleMemory::Buffer);
+            }
+        } else {
+            self.invalid_buffer_ticks = 0;
+        }
+
+        // a moved array gives garbage names. Rooms the tables don't know are fine, mods and new versions add some
+        if buffer_mode || rooms_ids::is_valid_room_name(&mem_values.room_name.current) {
+            self.unknown_room_ticks = 0;
+        } else {
+            self.unknown_room_ticks += 1;
+            if self.unknown_room_ticks >= UNKNOWN_ROOM_TICKS {
+                return Some(StaleMemory::RoomNames);
+            }
+        }
+
+        // the room id is read from its own address, so it still changes when the buffer is frozen
+        if buffer_mode {
+            let file_igt = mem_values.file_minutes.current * 60.0 + mem_values.file_seconds.current;
+            if file_igt != self.last_file_igt {
+                self.last_file_igt = file_igt;
+                self.room_changes_without_igt = 0;
+            } else if mem_values.room_id.changed()
+                && rooms_ids::is_gameplay_room(&mem_values.room_name.old)
+                && rooms_ids::is_gameplay_room(&mem_values.room_name.current)
+            {
+                self.room_changes_without_igt += 1;
+                if self.room_changes_without_igt >= ROOM_CHANGES_WITHOUT_IGT {
+                    return Some(StaleMemory::Buffer);
+                }
+            }
+        }
+
+        None
+    }
+}
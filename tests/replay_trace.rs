@@ -0,0 +1,70 @@
+//! Replays traces checked in under tests/traces and compares the timer actions with the ones the split logic gave when they were written
+
+use pizza_tower_ls_wasm::replay_report;
+
+#[test]
+fn entrance_full_game() {
+    let trace = include_str!("traces/entrance_full_game.txt");
+
+    assert_eq!(
+        replay_report(trace).unwrap(),
+        [
+            "tick 0: PauseGameTime",
+            "tick 0: SetGameTime(0.0)",
+            "tick 3: Reset",
+            "tick 5: SetGameTime(0.5)",
+            "tick 5: Start",
+            "tick 7: SetGameTime(4.0)",
+            "tick 9: SetGameTime(6.0)",
+            "tick 11: SetGameTime(20.0)",
+            "tick 13: SetGameTime(25.0)",
+            "tick 15: SetGameTime(26.0)",
+            "tick 15: Split",
+        ]
+    );
+}
+
+#[test]
+fn rejects_unknown_settings() {
+    let trace = "PTT # setting not_a_setting true\n";
+
+    assert!(replay_report(trace).is_err());
+}
+
+#[test]
+fn rejects_newer_trace_formats() {
+    let trace = "PTT # format 3\nPTT # buffer true\n";
+
+    assert!(replay_report(trace).is_err());
+}
+
+#[test]
+fn missing_columns_keep_their_default_values() {
+    let trace = "PTT # format 2\nPTT # buffer true\nPTT # columns room_id room_name\nPTT 2\t5\tMainmenu\nPTT 1\t6\tFinalintro\nPTT 1\t7\ttower_entrancehall\n";
+
+    assert_eq!(
+        replay_report(trace).unwrap(),
+        [
+            "tick 0: PauseGameTime",
+            "tick 0: SetGameTime(0.0)",
+            "tick 2: Reset",
+            "tick 3: Start"
+        ]
+    );
+}
+
+#[test]
+fn format_1_traces_have_the_old_columns() {
+    let trace = "PTT # format 1\nPTT # buffer true\nPTT 2\t6\t0\t0\t0\t0\t0\t0\tv1.1.063\tFinalintro\nPTT 1\t7\t0\t0.5\t0\t0\t0\t0\tv1.1.063\ttower_entrancehall\n";
+
+    assert_eq!(
+        replay_report(trace).unwrap(),
+        [
+            "tick 0: PauseGameTime",
+            "tick 0: SetGameTime(0.0)",
+            "tick 0: Reset",
+            "tick 2: SetGameTime(0.5)",
+            "tick 2: Start"
+        ]
+    );
+}